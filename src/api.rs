@@ -1,21 +1,42 @@
 // API endpoints
 
-use crate::game::adapter::{GameAdapter, Stage};
+use crate::events;
+use crate::game::adapter::Stage;
+use crate::game::history::MoveRecord;
+use crate::game::matchmaking::MatchmakingQueue;
+use crate::game::rating::LeaderboardEntry;
+use crate::game::registry::{self, GameInfo};
 use crate::game::search::{GameSummary, SearchOptions, SortKey, SortOrder};
-use crate::game::{adapter, connect4, snake, GameId, GameManager, GameType, SessionId};
+use crate::game::tournament::{
+    TournamentDetails, TournamentFormat, TournamentManager, TournamentSummary,
+};
+use crate::game::{
+    adapter, GameId, GameManager, GameSettings, GameType, GameUpdate, JoinToken, SessionId,
+    TournamentId,
+};
+use crate::socket::GameSocket;
 use actix_web::web::Json;
-use actix_web::{get, post, web, Result};
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Result};
+use actix_web_actors::ws;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 
 #[derive(Deserialize)]
 pub struct CreateGameRequest {
     game_type: GameType,
+    #[serde(default)]
+    options: Value,
+    move_timeout_ms: Option<u64>,
+    #[serde(default)]
+    private: bool,
 }
 
 #[derive(Serialize)]
 pub struct CreateGameResponse {
     game_id: GameId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    join_token: Option<JoinToken>,
 }
 
 #[post("/api/create-game")]
@@ -23,14 +44,39 @@ pub(crate) async fn create_game(
     payload: web::Json<CreateGameRequest>,
     gm_wrapped: web::Data<GameManager>,
 ) -> Result<Json<CreateGameResponse>> {
-    let game_id = match payload.game_type {
-        GameType::Connect4 => {
-            gm_wrapped.create_game(|id| Box::new(connect4::Connect4Adapter::new(id)))
-        }
-        GameType::Snake => gm_wrapped.create_game(|id| Box::new(snake::SnakeAdapter::new(id))),
-    }?;
+    let CreateGameRequest {
+        game_type,
+        options,
+        move_timeout_ms,
+        private,
+    } = payload.0;
+    let join_token = if private {
+        Some(JoinToken::new())
+    } else {
+        None
+    };
+    let settings = GameSettings {
+        move_timeout_ms,
+        join_token,
+    };
+    let game_id = gm_wrapped.create_game(game_type, options, settings)?;
+
+    Ok(Json(CreateGameResponse {
+        game_id,
+        join_token,
+    }))
+}
 
-    Ok(Json(CreateGameResponse { game_id }))
+#[derive(Serialize)]
+pub struct GameTypesResponse {
+    game_types: &'static [GameInfo],
+}
+
+#[get("/api/game-types")]
+pub(crate) async fn game_types() -> Result<Json<GameTypesResponse>> {
+    Ok(Json(GameTypesResponse {
+        game_types: registry::all(),
+    }))
 }
 
 #[derive(Deserialize)]
@@ -80,9 +126,121 @@ pub(crate) async fn list_games(
     }))
 }
 
+#[derive(Deserialize)]
+pub struct LeaderboardQuery {
+    game_type: GameType,
+}
+
+#[derive(Serialize)]
+pub struct LeaderboardResponse {
+    entries: Vec<LeaderboardEntry>,
+}
+
+#[get("/api/leaderboard")]
+pub(crate) async fn leaderboard(
+    query: web::Query<LeaderboardQuery>,
+    gm_wrapped: web::Data<GameManager>,
+) -> Result<Json<LeaderboardResponse>> {
+    Ok(Json(LeaderboardResponse {
+        entries: gm_wrapped.get_leaderboard(query.game_type),
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct EnqueueRequest {
+    game_type: GameType,
+    players: Option<usize>,
+    username: String,
+}
+
+#[derive(Serialize)]
+pub struct EnqueueResponse {
+    game_id: GameId,
+    session_id: SessionId,
+}
+
+#[post("/api/matchmaking/enqueue")]
+pub(crate) async fn enqueue(
+    payload: web::Json<EnqueueRequest>,
+    gm_wrapped: web::Data<GameManager>,
+    mm_wrapped: web::Data<MatchmakingQueue>,
+) -> Result<Json<EnqueueResponse>> {
+    let EnqueueRequest {
+        game_type,
+        players,
+        username,
+    } = payload.0;
+
+    let seat = mm_wrapped
+        .enqueue(&gm_wrapped, game_type, players, username)
+        .await?;
+
+    Ok(Json(EnqueueResponse {
+        game_id: seat.game_id,
+        session_id: seat.session_id,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct CreateTournamentRequest {
+    game_type: GameType,
+    format: TournamentFormat,
+    players: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct CreateTournamentResponse {
+    tournament_id: TournamentId,
+    sessions: HashMap<String, SessionId>,
+}
+
+#[post("/api/tournaments")]
+pub(crate) async fn create_tournament(
+    payload: web::Json<CreateTournamentRequest>,
+    gm_wrapped: web::Data<GameManager>,
+    tm_wrapped: web::Data<TournamentManager>,
+) -> Result<Json<CreateTournamentResponse>> {
+    let CreateTournamentRequest {
+        game_type,
+        format,
+        players,
+    } = payload.0;
+
+    let (tournament_id, sessions) =
+        tm_wrapped.create_tournament(&gm_wrapped, game_type, format, players)?;
+
+    Ok(Json(CreateTournamentResponse {
+        tournament_id,
+        sessions,
+    }))
+}
+
+#[derive(Serialize)]
+pub struct ListTournamentsResponse {
+    tournaments: Vec<TournamentSummary>,
+}
+
+#[get("/api/tournaments")]
+pub(crate) async fn list_tournaments(
+    tm_wrapped: web::Data<TournamentManager>,
+) -> Result<Json<ListTournamentsResponse>> {
+    Ok(Json(ListTournamentsResponse {
+        tournaments: tm_wrapped.list_tournaments(),
+    }))
+}
+
+#[get("/api/tournaments/{tournament_id}")]
+pub(crate) async fn get_tournament(
+    web::Path(tournament_id): web::Path<TournamentId>,
+    tm_wrapped: web::Data<TournamentManager>,
+) -> Result<Json<TournamentDetails>> {
+    Ok(Json(tm_wrapped.get_tournament(tournament_id)?))
+}
+
 #[derive(Deserialize)]
 pub struct JoinGameRequest {
     username: String,
+    join_token: Option<JoinToken>,
 }
 
 #[derive(Serialize)]
@@ -97,16 +255,22 @@ pub(crate) async fn join_game(
     gm_wrapped: web::Data<GameManager>,
 ) -> Result<Json<JoinGameResponse>> {
     gm_wrapped
-        .receive_join(game_id, payload.username.clone())
-        .and_then(|session_id| Ok(Json(JoinGameResponse { session_id })))
+        .receive_join(game_id, payload.username.clone(), payload.join_token)
+        .map(|session_id| Json(JoinGameResponse { session_id }))
+}
+
+#[derive(Deserialize)]
+pub struct GetStateQuery {
+    session_id: Option<SessionId>,
 }
 
 #[get("/api/{game_id}/get-state")]
 pub(crate) async fn get_state(
     web::Path(game_id): web::Path<GameId>,
+    query: web::Query<GetStateQuery>,
     gm_wrapped: web::Data<GameManager>,
 ) -> Result<Json<adapter::GenericGameState>> {
-    Ok(Json(gm_wrapped.get_state(game_id)?))
+    Ok(Json(gm_wrapped.get_state(game_id, query.session_id)?))
 }
 
 #[derive(Deserialize)]
@@ -128,7 +292,7 @@ pub(crate) async fn submit_move(
 ) -> Result<Json<SubmitMoveResponse>> {
     gm_wrapped
         .receive_move(game_id, payload.session_id, payload.payload.clone())
-        .and_then(|()| Ok(Json(SubmitMoveResponse { success: true })))
+        .map(|()| Json(SubmitMoveResponse { success: true }))
 }
 
 #[derive(Deserialize)]
@@ -151,3 +315,54 @@ pub(crate) async fn wait_for_update(
         clock: gm_wrapped.subscribe(game_id)?.wait(query.since).await?,
     }))
 }
+
+#[derive(Serialize)]
+pub struct HistoryResponse {
+    moves: Vec<MoveRecord>,
+}
+
+#[get("/api/{game_id}/history")]
+pub(crate) async fn get_history(
+    web::Path(game_id): web::Path<GameId>,
+    gm_wrapped: web::Data<GameManager>,
+) -> Result<Json<HistoryResponse>> {
+    Ok(Json(HistoryResponse {
+        moves: gm_wrapped.get_history(game_id)?,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct StateAtQuery {
+    clock: usize,
+}
+
+#[get("/api/{game_id}/state-at")]
+pub(crate) async fn get_state_at(
+    web::Path(game_id): web::Path<GameId>,
+    query: web::Query<StateAtQuery>,
+    gm_wrapped: web::Data<GameManager>,
+) -> Result<Json<GameUpdate>> {
+    Ok(Json(gm_wrapped.get_state_at(game_id, query.clock)?))
+}
+
+#[get("/api/{game_id}/ws")]
+pub(crate) async fn game_socket(
+    web::Path(game_id): web::Path<GameId>,
+    request: HttpRequest,
+    stream: web::Payload,
+    gm_wrapped: web::Data<GameManager>,
+) -> Result<HttpResponse> {
+    gm_wrapped.get_state(game_id, None)?;
+    ws::start(GameSocket::new(game_id, gm_wrapped), &request, stream)
+}
+
+#[get("/api/{game_id}/events")]
+pub(crate) async fn game_events(
+    web::Path(game_id): web::Path<GameId>,
+    gm_wrapped: web::Data<GameManager>,
+) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .header("Cache-Control", "no-cache")
+        .streaming(events::game_events(game_id, gm_wrapped)?))
+}
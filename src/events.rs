@@ -0,0 +1,79 @@
+// Server-sent events for spectating games
+
+use crate::game::adapter::Stage;
+use crate::game::{GameId, GameManager};
+use actix_web::web::{self, Bytes};
+use futures::{future, stream, Stream, StreamExt};
+use serde_json::json;
+use std::time::Duration;
+use tokio::time::{self, Instant};
+
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+enum Tick {
+    Update,
+    KeepAlive,
+    Closed,
+}
+
+fn format_event(event: &str, id: Option<usize>, data: &str) -> Bytes {
+    let id_line = id.map_or(String::new(), |id| format!("id: {}\n", id));
+    Bytes::from(format!("{}event: {}\ndata: {}\n\n", id_line, event, data))
+}
+
+pub fn game_events(
+    game_id: GameId,
+    game_manager: web::Data<GameManager>,
+) -> actix_web::Result<impl Stream<Item = actix_web::Result<Bytes>> + Unpin> {
+    // Subscribe before the initial state is read so that no update is missed
+    let updates = game_manager.subscribe(game_id)?.into_stream();
+
+    let ticks = stream::select(
+        stream::once(future::ready(Tick::Update))
+            .chain(updates.map(|_| Tick::Update))
+            .chain(stream::once(future::ready(Tick::Closed))),
+        time::interval_at(Instant::now() + KEEP_ALIVE_INTERVAL, KEEP_ALIVE_INTERVAL)
+            .map(|_| Tick::KeepAlive),
+    );
+
+    Ok(Box::pin(stream::unfold(
+        (Box::pin(ticks), false),
+        move |(mut ticks, ended)| {
+            let game_manager = game_manager.clone();
+
+            async move {
+                if ended {
+                    return None;
+                }
+
+                match ticks.next().await? {
+                    Tick::Closed => None,
+                    Tick::KeepAlive => {
+                        Some((Ok(Bytes::from_static(b": keep-alive\n\n")), (ticks, false)))
+                    }
+                    Tick::Update => {
+                        let update = game_manager.get_update(game_id).ok()?;
+                        let mut bytes = format_event(
+                            "state",
+                            Some(update.clock),
+                            &serde_json::to_string(&update.state).unwrap(),
+                        );
+                        let ended = update.state.stage == Stage::Ended;
+
+                        if ended {
+                            let data = json!({
+                                "winners": update.state.winners,
+                                "termination": update.state.termination,
+                            });
+                            bytes = [bytes, format_event("ended", None, &data.to_string())]
+                                .concat()
+                                .into();
+                        }
+
+                        Some((Ok(bytes), (ticks, ended)))
+                    }
+                }
+            }
+        },
+    )))
+}
@@ -1,7 +1,8 @@
-use crate::game::{GameId, GameType};
+use crate::game::GameId;
 use crate::notify::Notifier;
 use actix_web::http::StatusCode;
 use actix_web::{ResponseError, Result};
+use chrono::{DateTime, Utc};
 use derive_more::Display;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -15,6 +16,7 @@ fn format_invalid_game_stage(stage: &Stage) -> &'static str {
     }
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Clone, Display)]
 pub enum GameAdapterErrorType {
     #[display(fmt = "player {} cannot move at the moment", _0)]
@@ -23,6 +25,8 @@ pub enum GameAdapterErrorType {
     InvalidMove(String),
     #[display(fmt = "{}", "format_invalid_game_stage(_0)")]
     InvalidGameStage(Stage),
+    #[display(fmt = "invalid options: {}", _0)]
+    InvalidOptions(String),
 }
 
 #[derive(Debug, Clone, Display)]
@@ -55,12 +59,24 @@ pub enum Stage {
     Ended,
 }
 
+/// Why a game ended before it was played out.
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case", tag = "reason")]
+pub enum Termination {
+    Resignation { player: String },
+    DrawAgreed,
+}
+
 #[derive(Serialize)]
 pub struct GenericGameState {
     pub players: Vec<String>,
     pub can_move: Vec<String>,
     pub winners: Vec<String>,
     pub stage: Stage,
+    pub deadline: Option<DateTime<Utc>>,
+    pub termination: Option<Termination>,
+    // The players who have offered or accepted a draw since the last move declining it
+    pub draw_offers: Vec<String>,
     pub payload: Value,
 }
 
@@ -71,14 +87,44 @@ pub struct GenericGameMove {
 }
 
 pub trait GameAdapter: Send {
-    fn new(game_id: GameId) -> Self
+    fn new(game_id: GameId, options: Value) -> Result<Self>
+    where
+        Self: Sized;
+    fn load_state(state: Value) -> Result<Self>
     where
         Self: Sized;
+    fn save_state(&self) -> Result<Value>;
     fn get_notifier(&self) -> &Notifier;
     fn add_player(&mut self, username: String) -> Result<()>;
     fn has_player(&self, username: &str) -> bool;
     fn play_move(&mut self, game_move: GenericGameMove) -> Result<()>;
+    /// Called when the players in `can_move` have not moved before the move deadline.
+    fn handle_timeout(&mut self) -> Result<()>;
+    /// Ends a game in progress with the given winners before it is played out, such as when a
+    /// player resigns or the players agree to a draw. The manager checks that the game is in
+    /// progress and notifies the players.
+    fn end_game(&mut self, winners: Vec<String>);
     fn get_stage(&self) -> Stage;
     fn get_encoded_state(&self) -> Result<GenericGameState>;
-    fn get_type(&self) -> GameType;
+    /// Returns the players still in contention, who share the win when one of them resigns and
+    /// must all agree to a draw. Games where players can be knocked out override this.
+    fn get_contenders(&self) -> Result<Vec<String>> {
+        Ok(self.get_encoded_state()?.players)
+    }
+    /// Returns the state as seen by one of the players, or by a spectator if `viewer` is `None`.
+    /// Games with hidden information override this to redact what the viewer may not see.
+    fn get_viewer_state(&self, viewer: Option<&str>) -> Result<GenericGameState> {
+        let _ = viewer;
+        self.get_encoded_state()
+    }
+    /// Whether move payloads can reveal hidden information, such as the cards a player passes, in
+    /// which case they are left out of the move history until the game has ended.
+    fn has_private_moves(&self) -> bool {
+        false
+    }
+    /// For real-time games, the interval in milliseconds at which the game advances on its own,
+    /// whether or not the players have moved. The game manager calls `handle_timeout` each time.
+    fn get_tick_interval_ms(&self) -> Option<u64> {
+        None
+    }
 }
@@ -0,0 +1,690 @@
+use crate::game::adapter::{
+    GameAdapter, GameAdapterError, GameAdapterErrorType, GenericGameMove, GenericGameState, Stage,
+};
+use crate::game::GameId;
+use crate::notify::Notifier;
+use actix_web::error::{InternalError, JsonPayloadError};
+use actix_web::HttpResponse;
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::vec;
+use std::vec::Vec;
+
+pub const NUM_PLAYERS: usize = 2;
+const DEFAULT_SIZE: usize = 10;
+const MIN_SIZE: usize = 5;
+const MAX_SIZE: usize = 20;
+const DEFAULT_SHIPS: [usize; 5] = [5, 4, 3, 3, 2];
+const RANDOM_PLACEMENT_ATTEMPTS: usize = 100;
+
+#[derive(Serialize, Deserialize)]
+pub struct BattleshipAdapter {
+    game_id: GameId,
+    players: Vec<String>,
+    stage: Stage,
+    #[serde(skip, default = "Notifier::new")]
+    notifier: Notifier,
+    game: Battleship,
+    winner: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct BattleshipRequestPayload {
+    #[serde(flatten)]
+    action: BattleshipAction,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum BattleshipAction {
+    Place { ships: Vec<Placement> },
+    Fire { row: usize, column: usize },
+}
+
+#[derive(Deserialize, Clone, Copy)]
+struct Placement {
+    row: usize,
+    column: usize,
+    orientation: Orientation,
+}
+
+#[derive(Serialize)]
+struct BattleshipResponsePayload<'a> {
+    phase: Phase,
+    ships: Option<&'a Vec<Ship>>,
+    shots: HashMap<&'a String, &'a Vec<Shot>>,
+    sunk_ships: HashMap<&'a String, Vec<&'a Ship>>,
+    options: &'a BattleshipOptions,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+struct BattleshipOptions {
+    size: usize,
+    ships: Vec<usize>,
+}
+
+impl Default for BattleshipOptions {
+    fn default() -> Self {
+        BattleshipOptions {
+            size: DEFAULT_SIZE,
+            ships: DEFAULT_SHIPS.to_vec(),
+        }
+    }
+}
+
+impl BattleshipOptions {
+    fn validate(&self) -> Result<(), String> {
+        if !(MIN_SIZE..=MAX_SIZE).contains(&self.size) {
+            Err(format!(
+                "size must be between {} and {}",
+                MIN_SIZE, MAX_SIZE
+            ))
+        } else if self.ships.is_empty() || self.ships.len() > self.size {
+            Err("there must be between 1 ship and as many ships as the size".to_string())
+        } else if self.ships.iter().any(|x| !(1..=self.size).contains(x)) {
+            Err("ships must have a length between 1 and the size".to_string())
+        } else if self.ships.iter().sum::<usize>() * 2 > self.size * self.size {
+            Err("ships cannot cover more than half of the board".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum Phase {
+    Placement,
+    Playing,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum Orientation {
+    // Extends to the right of the first cell
+    Horizontal,
+    // Extends down from the first cell
+    Vertical,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+struct Ship {
+    row: usize,
+    column: usize,
+    orientation: Orientation,
+    length: usize,
+}
+
+impl Ship {
+    fn cells(&self) -> Vec<(usize, usize)> {
+        (0..self.length)
+            .map(|i| match self.orientation {
+                Orientation::Horizontal => (self.row, self.column + i),
+                Orientation::Vertical => (self.row + i, self.column),
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum ShotResult {
+    Miss,
+    Hit,
+    Sunk,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct Shot {
+    row: usize,
+    column: usize,
+    result: ShotResult,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Battleship {
+    game_id: GameId,
+    phase: Phase,
+    boards: Vec<Option<Vec<Ship>>>, // the ships placed by each player
+    shots: Vec<Vec<Shot>>,          // the shots fired by each player
+    // Index of the player to fire next
+    turn: usize,
+    options: BattleshipOptions,
+    // Random placements are generated from the seed, so that replaying the same moves places the
+    // same ships
+    #[serde(default = "random_seed")]
+    seed: u64,
+}
+
+fn random_seed() -> u64 {
+    thread_rng().gen()
+}
+
+impl GameAdapter for BattleshipAdapter {
+    fn new(game_id: GameId, options: Value) -> actix_web::Result<Self>
+    where
+        Self: Sized,
+    {
+        let options = serde_json::from_value::<Option<BattleshipOptions>>(options)
+            .map_err(|e| e.to_string())
+            .and_then(|options| {
+                let options = options.unwrap_or_default();
+                options.validate()?;
+                Ok(options)
+            })
+            .map_err(|e| {
+                GameAdapterError::actix_err(game_id, GameAdapterErrorType::InvalidOptions(e))
+            })?;
+
+        Ok(BattleshipAdapter {
+            game_id,
+            players: vec![],
+            stage: Stage::Waiting,
+            notifier: Notifier::new(),
+            game: Battleship::new(game_id, options),
+            winner: vec![],
+        })
+    }
+
+    fn load_state(state: Value) -> actix_web::Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(serde_json::from_value(state)?)
+    }
+
+    fn save_state(&self) -> actix_web::Result<Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn get_notifier(&self) -> &Notifier {
+        &self.notifier
+    }
+
+    fn add_player(&mut self, username: String) -> actix_web::Result<()> {
+        assert!(self.players.len() < NUM_PLAYERS);
+        assert_eq!(self.stage, Stage::Waiting);
+
+        self.players.push(username);
+        if self.players.len() == NUM_PLAYERS {
+            self.stage = Stage::InProgress;
+        }
+        self.notifier.send();
+        Ok(())
+    }
+
+    fn has_player(&self, username: &str) -> bool {
+        self.players.iter().any(|s| s.eq(username))
+    }
+
+    fn play_move(&mut self, game_move: GenericGameMove) -> actix_web::Result<()> {
+        if self.stage != Stage::InProgress {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidGameStage(self.stage),
+            ));
+        }
+
+        let request_payload = serde_json::from_value::<BattleshipRequestPayload>(game_move.payload)
+            .map_err(|e| {
+                InternalError::from_response(
+                    "",
+                    HttpResponse::BadRequest()
+                        .content_type("text/plain")
+                        .body(JsonPayloadError::Deserialize(e).to_string()),
+                )
+            })?;
+
+        let player = match self.players.iter().position(|x| *x == game_move.player) {
+            Some(player) if self.game.can_move().contains(&player) => player,
+            _ => {
+                return Err(GameAdapterError::actix_err(
+                    self.game_id,
+                    GameAdapterErrorType::InvalidPlayer(game_move.player),
+                ))
+            }
+        };
+
+        match request_payload.action {
+            BattleshipAction::Place { ships } => self.game.place(player, &ships)?,
+            BattleshipAction::Fire { row, column } => {
+                self.game.fire(row, column)?;
+                if self.game.all_sunk(1 - player) {
+                    self.winner.push(game_move.player);
+                    self.stage = Stage::Ended;
+                }
+            }
+        }
+
+        self.notifier.send();
+        Ok(())
+    }
+
+    fn handle_timeout(&mut self) -> actix_web::Result<()> {
+        if self.stage != Stage::InProgress {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidGameStage(self.stage),
+            ));
+        }
+
+        match self.game.phase {
+            // Players who have not placed their ships in time get a random placement
+            Phase::Placement => {
+                for player in self.game.can_move() {
+                    let placements = self.game.random_placements(player);
+                    self.game.place(player, &placements)?;
+                }
+            }
+            // The player who ran out of time forfeits
+            Phase::Playing => {
+                self.winner = vec![self.players[1 - self.game.turn].clone()];
+                self.stage = Stage::Ended;
+            }
+        }
+
+        self.notifier.send();
+        Ok(())
+    }
+
+    fn end_game(&mut self, winners: Vec<String>) {
+        self.winner = winners;
+        self.stage = Stage::Ended;
+    }
+
+    fn get_stage(&self) -> Stage {
+        self.stage
+    }
+
+    fn get_encoded_state(&self) -> actix_web::Result<GenericGameState> {
+        self.get_viewer_state(None)
+    }
+
+    fn get_viewer_state(&self, viewer: Option<&str>) -> actix_web::Result<GenericGameState> {
+        let game = &self.game;
+        let viewer = viewer.and_then(|viewer| self.players.iter().position(|x| x == viewer));
+
+        let response_payload = BattleshipResponsePayload {
+            phase: game.phase,
+            ships: viewer.and_then(|x| game.boards[x].as_ref()),
+            shots: self.players.iter().zip(game.shots.iter()).collect(),
+            sunk_ships: self
+                .players
+                .iter()
+                .enumerate()
+                .map(|(player, username)| (username, game.sunk_ships(player)))
+                .collect(),
+            options: &game.options,
+        };
+
+        Ok(GenericGameState {
+            players: self.players.clone(),
+            stage: self.stage,
+            can_move: if self.stage == Stage::InProgress {
+                game.can_move()
+                    .iter()
+                    .map(|&x| self.players[x].clone())
+                    .collect()
+            } else {
+                vec![]
+            },
+            winners: self.winner.clone(),
+            deadline: None,
+            termination: None,
+            draw_offers: vec![],
+            payload: serde_json::to_value(&response_payload)?,
+        })
+    }
+
+    fn has_private_moves(&self) -> bool {
+        true
+    }
+}
+
+impl Battleship {
+    fn new(game_id: GameId, options: BattleshipOptions) -> Self {
+        Battleship {
+            game_id,
+            phase: Phase::Placement,
+            boards: vec![None; NUM_PLAYERS],
+            shots: vec![vec![]; NUM_PLAYERS],
+            turn: 0,
+            options,
+            seed: random_seed(),
+        }
+    }
+
+    fn can_move(&self) -> Vec<usize> {
+        match self.phase {
+            Phase::Placement => (0..NUM_PLAYERS)
+                .filter(|&x| self.boards[x].is_none())
+                .collect(),
+            Phase::Playing => vec![self.turn],
+        }
+    }
+
+    fn invalid_move(&self, reason: String) -> actix_web::Error {
+        GameAdapterError::actix_err(self.game_id, GameAdapterErrorType::InvalidMove(reason))
+    }
+
+    /// Places a player's ships, given in the order of the ship lengths in the options.
+    fn place(&mut self, player: usize, placements: &[Placement]) -> actix_web::Result<()> {
+        if self.phase != Phase::Placement {
+            return Err(self.invalid_move("ships have already been placed".to_string()));
+        }
+        if placements.len() != self.options.ships.len() {
+            return Err(self.invalid_move(format!(
+                "exactly {} ships must be placed",
+                self.options.ships.len()
+            )));
+        }
+
+        let ships: Vec<Ship> = placements
+            .iter()
+            .zip(self.options.ships.iter())
+            .map(|(x, &length)| Ship {
+                row: x.row,
+                column: x.column,
+                orientation: x.orientation,
+                length,
+            })
+            .collect();
+        self.check_ships(&ships).map_err(|e| self.invalid_move(e))?;
+
+        self.boards[player] = Some(ships);
+        if self.boards.iter().all(Option::is_some) {
+            self.phase = Phase::Playing;
+        }
+        Ok(())
+    }
+
+    fn check_ships(&self, ships: &[Ship]) -> Result<(), String> {
+        let mut occupied = HashSet::new();
+
+        for (i, ship) in ships.iter().enumerate() {
+            // Checked before the cells are worked out so that they cannot overflow
+            if ship.row >= self.options.size || ship.column >= self.options.size {
+                return Err(format!("ship {} does not fit on the board", i));
+            }
+            for cell in ship.cells() {
+                if cell.0 >= self.options.size || cell.1 >= self.options.size {
+                    return Err(format!("ship {} does not fit on the board", i));
+                }
+                if !occupied.insert(cell) {
+                    return Err(format!("ship {} overlaps another ship", i));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Chooses random positions for the ships that do not overlap, falling back to placing each
+    /// ship on its own row. The positions are derived from the seed and the player.
+    fn random_placements(&self, player: usize) -> Vec<Placement> {
+        let size = self.options.size;
+        let mut seed = <StdRng as SeedableRng>::Seed::default();
+        seed[..8].copy_from_slice(&self.seed.to_le_bytes());
+        seed[8..16].copy_from_slice(&(player as u64).to_le_bytes());
+        let mut rng = StdRng::from_seed(seed);
+
+        for _ in 0..RANDOM_PLACEMENT_ATTEMPTS {
+            let mut ships: Vec<Ship> = vec![];
+            for &length in self.options.ships.iter() {
+                let orientation = if rng.gen_bool(0.5) {
+                    Orientation::Horizontal
+                } else {
+                    Orientation::Vertical
+                };
+                let (rows, columns) = match orientation {
+                    Orientation::Horizontal => (size, size - length + 1),
+                    Orientation::Vertical => (size - length + 1, size),
+                };
+                ships.push(Ship {
+                    row: rng.gen_range(0..rows),
+                    column: rng.gen_range(0..columns),
+                    orientation,
+                    length,
+                });
+            }
+
+            if self.check_ships(&ships).is_ok() {
+                return ships
+                    .iter()
+                    .map(|x| Placement {
+                        row: x.row,
+                        column: x.column,
+                        orientation: x.orientation,
+                    })
+                    .collect();
+            }
+        }
+
+        (0..self.options.ships.len())
+            .map(|row| Placement {
+                row,
+                column: 0,
+                orientation: Orientation::Horizontal,
+            })
+            .collect()
+    }
+
+    /// Fires a shot for the player whose turn it is at the opponent's board.
+    fn fire(&mut self, row: usize, column: usize) -> actix_web::Result<()> {
+        if self.phase != Phase::Playing {
+            return Err(
+                self.invalid_move("shots cannot be fired until every ship is placed".to_string())
+            );
+        }
+        if row >= self.options.size || column >= self.options.size {
+            return Err(self.invalid_move(format!("cell ({}, {}) does not exist", row, column)));
+        }
+        let shots = &self.shots[self.turn];
+        if shots.iter().any(|x| x.row == row && x.column == column) {
+            return Err(self.invalid_move(format!(
+                "cell ({}, {}) has already been fired at",
+                row, column
+            )));
+        }
+
+        let fired_at = |cell: &(usize, usize)| {
+            *cell == (row, column) || shots.iter().any(|x| (x.row, x.column) == *cell)
+        };
+        let opponent = self.boards[1 - self.turn]
+            .as_ref()
+            .expect("ships are placed");
+        let result = match opponent.iter().find(|x| x.cells().contains(&(row, column))) {
+            None => ShotResult::Miss,
+            Some(ship) if ship.cells().iter().all(fired_at) => ShotResult::Sunk,
+            Some(_) => ShotResult::Hit,
+        };
+
+        self.shots[self.turn].push(Shot {
+            row,
+            column,
+            result,
+        });
+        self.turn = 1 - self.turn;
+        Ok(())
+    }
+
+    /// The player's ships that the opponent has hit in every cell.
+    fn sunk_ships(&self, player: usize) -> Vec<&Ship> {
+        let shots = &self.shots[1 - player];
+        let sunk = |ship: &&Ship| {
+            ship.cells()
+                .iter()
+                .all(|&cell| shots.iter().any(|x| (x.row, x.column) == cell))
+        };
+
+        self.boards[player].iter().flatten().filter(sunk).collect()
+    }
+
+    fn all_sunk(&self, player: usize) -> bool {
+        self.boards[player]
+            .as_ref()
+            .is_some_and(|x| self.sunk_ships(player).len() == x.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placement(row: usize, column: usize, orientation: Orientation) -> Placement {
+        Placement {
+            row,
+            column,
+            orientation,
+        }
+    }
+
+    fn create_game() -> Battleship {
+        Battleship::new(
+            GameId::new(),
+            BattleshipOptions {
+                size: 5,
+                ships: vec![3, 2],
+            },
+        )
+    }
+
+    fn place_both(game: &mut Battleship) {
+        let placements = [
+            placement(0, 0, Orientation::Horizontal),
+            placement(2, 4, Orientation::Vertical),
+        ];
+        game.place(0, &placements).unwrap();
+        game.place(1, &placements).unwrap();
+    }
+
+    #[test]
+    fn placements_must_fit_without_overlapping() {
+        let mut game = create_game();
+
+        for placements in [
+            vec![
+                placement(0, 3, Orientation::Horizontal),
+                placement(4, 0, Orientation::Horizontal),
+            ],
+            vec![
+                placement(3, 0, Orientation::Vertical),
+                placement(0, 0, Orientation::Horizontal),
+            ],
+            vec![
+                placement(1, 1, Orientation::Horizontal),
+                placement(0, 2, Orientation::Vertical),
+            ],
+            vec![placement(0, 0, Orientation::Horizontal)],
+            vec![
+                placement(0, usize::MAX, Orientation::Horizontal),
+                placement(usize::MAX, 0, Orientation::Vertical),
+            ],
+        ] {
+            assert!(game.place(0, &placements).is_err());
+        }
+
+        assert!(game.fire(0, 0).is_err());
+        place_both(&mut game);
+        assert_eq!(game.phase, Phase::Playing);
+        assert!(game.place(0, &[]).is_err());
+    }
+
+    #[test]
+    fn random_placements_follow_the_seed() {
+        let positions = |game: &Battleship, player| -> Vec<(usize, usize)> {
+            game.random_placements(player)
+                .iter()
+                .map(|x| (x.row, x.column))
+                .collect()
+        };
+        let mut game = create_game();
+        let mut other = create_game();
+        other.seed = game.seed;
+
+        assert_eq!(positions(&game, 0), positions(&other, 0));
+        assert_eq!(positions(&game, 1), positions(&other, 1));
+        game.place(0, &game.random_placements(0)).unwrap();
+    }
+
+    #[test]
+    fn shots_report_hits_misses_and_sunk_ships() {
+        let mut game = create_game();
+        place_both(&mut game);
+
+        for (row, column) in [(2, 4), (0, 0), (3, 4), (4, 4), (1, 1)] {
+            game.fire(row, column).unwrap();
+        }
+        assert!(game.fire(5, 0).is_err());
+        assert!(game.fire(1, 1).is_ok());
+        assert!(game.fire(3, 4).is_err());
+
+        let results: Vec<ShotResult> = game.shots[0].iter().map(|x| x.result).collect();
+        assert_eq!(
+            results,
+            vec![ShotResult::Hit, ShotResult::Sunk, ShotResult::Miss]
+        );
+        assert_eq!(game.sunk_ships(1).len(), 1);
+        assert!(!game.all_sunk(1));
+    }
+
+    #[test]
+    fn sinking_every_ship_wins() {
+        let mut adapter = BattleshipAdapter::new(
+            GameId::new(),
+            serde_json::json!({ "size": 5, "ships": [2] }),
+        )
+        .unwrap();
+        adapter.add_player("a".to_string()).unwrap();
+        adapter.add_player("b".to_string()).unwrap();
+        let play = |adapter: &mut BattleshipAdapter, player: &str, payload: Value| {
+            adapter.play_move(GenericGameMove {
+                player: player.to_string(),
+                payload,
+            })
+        };
+
+        let place = serde_json::json!({
+            "game_type": "battleship",
+            "action": "place",
+            "ships": [{ "row": 1, "column": 1, "orientation": "vertical" }],
+        });
+        play(&mut adapter, "a", place.clone()).unwrap();
+        play(&mut adapter, "b", place).unwrap();
+        for (player, row) in [("a", 1), ("b", 0), ("a", 2)] {
+            let fire = serde_json::json!({
+                "game_type": "battleship", "action": "fire", "row": row, "column": 1
+            });
+            play(&mut adapter, player, fire).unwrap();
+        }
+
+        assert_eq!(adapter.stage, Stage::Ended);
+        assert_eq!(adapter.winner, vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn viewers_only_see_their_own_ships() {
+        let mut adapter = BattleshipAdapter::new(GameId::new(), Value::Null).unwrap();
+        adapter.add_player("a".to_string()).unwrap();
+        adapter.add_player("b".to_string()).unwrap();
+        adapter.handle_timeout().unwrap();
+
+        let spectator = adapter.get_viewer_state(None).unwrap().payload;
+        assert_eq!(spectator["ships"], Value::Null);
+        let ships = &adapter.get_viewer_state(Some("b")).unwrap().payload["ships"];
+        assert_eq!(
+            ships,
+            &serde_json::to_value(adapter.game.boards[1].as_ref().unwrap()).unwrap()
+        );
+        assert_eq!(adapter.game.phase, Phase::Playing);
+        assert!(adapter
+            .game
+            .boards
+            .iter()
+            .flatten()
+            .all(|x| adapter.game.check_ships(x).is_ok()));
+    }
+}
@@ -0,0 +1,1144 @@
+use crate::game::adapter::{
+    GameAdapter, GameAdapterError, GameAdapterErrorType, GenericGameMove, GenericGameState, Stage,
+};
+use crate::game::GameId;
+use crate::notify::Notifier;
+use actix_web::error::{InternalError, JsonPayloadError};
+use actix_web::HttpResponse;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::vec;
+use std::vec::Vec;
+
+pub const NUM_PLAYERS: usize = 2;
+const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+// Half-moves without a capture or pawn move after which the game is drawn
+const FIFTY_MOVE_RULE_PLIES: u32 = 100;
+const REPETITION_LIMIT: usize = 3;
+
+const KNIGHT_STEPS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const KING_STEPS: [(i8, i8); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const PROMOTIONS: [PieceKind; 4] = [
+    PieceKind::Queen,
+    PieceKind::Rook,
+    PieceKind::Bishop,
+    PieceKind::Knight,
+];
+
+#[derive(Serialize, Deserialize)]
+pub struct ChessAdapter {
+    game_id: GameId,
+    players: Vec<String>,
+    stage: Stage,
+    #[serde(skip, default = "Notifier::new")]
+    notifier: Notifier,
+    game: Chess,
+    winner: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ChessRequestPayload {
+    #[serde(rename = "move")]
+    uci_move: String,
+}
+
+#[derive(Serialize)]
+struct ChessResponsePayload<'a> {
+    fen: String,
+    legal_moves: Vec<String>,
+    check: bool,
+    last_move: Option<String>,
+    outcome: Option<Outcome>,
+    options: &'a ChessOptions,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+struct ChessOptions {
+    fen: String,
+}
+
+impl Default for ChessOptions {
+    fn default() -> Self {
+        ChessOptions {
+            fen: START_FEN.to_string(),
+        }
+    }
+}
+
+impl ChessOptions {
+    fn validate(&self) -> Result<(), String> {
+        let position = Position::from_fen(&self.fen)?;
+        if position.legal_moves().is_empty() {
+            Err("fen must be a position where the side to move has a legal move".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum Outcome {
+    Checkmate,
+    Stalemate,
+    FiftyMoveRule,
+    ThreefoldRepetition,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+enum Color {
+    White,
+    Black,
+}
+
+impl Color {
+    /// The index of the player with this color, where the first player plays white.
+    fn index(self) -> usize {
+        match self {
+            Color::White => 0,
+            Color::Black => 1,
+        }
+    }
+
+    fn opponent(self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+
+    fn pawn_direction(self) -> i8 {
+        match self {
+            Color::White => 1,
+            Color::Black => -1,
+        }
+    }
+
+    fn back_rank(self) -> i8 {
+        match self {
+            Color::White => 0,
+            Color::Black => 7,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+enum PieceKind {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
+impl PieceKind {
+    fn from_char(c: char) -> Option<PieceKind> {
+        match c.to_ascii_lowercase() {
+            'p' => Some(PieceKind::Pawn),
+            'n' => Some(PieceKind::Knight),
+            'b' => Some(PieceKind::Bishop),
+            'r' => Some(PieceKind::Rook),
+            'q' => Some(PieceKind::Queen),
+            'k' => Some(PieceKind::King),
+            _ => None,
+        }
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            PieceKind::Pawn => 'p',
+            PieceKind::Knight => 'n',
+            PieceKind::Bishop => 'b',
+            PieceKind::Rook => 'r',
+            PieceKind::Queen => 'q',
+            PieceKind::King => 'k',
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+struct Piece {
+    color: Color,
+    kind: PieceKind,
+}
+
+impl Piece {
+    fn from_fen_char(c: char) -> Option<Piece> {
+        let kind = PieceKind::from_char(c)?;
+        let color = if c.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+        Some(Piece { color, kind })
+    }
+
+    fn to_fen_char(self) -> char {
+        match self.color {
+            Color::White => self.kind.to_char().to_ascii_uppercase(),
+            Color::Black => self.kind.to_char(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+struct Square {
+    file: i8,
+    rank: i8,
+}
+
+impl Square {
+    fn new(file: i8, rank: i8) -> Option<Square> {
+        if (0..8).contains(&file) && (0..8).contains(&rank) {
+            Some(Square { file, rank })
+        } else {
+            None
+        }
+    }
+
+    fn offset(self, file_step: i8, rank_step: i8) -> Option<Square> {
+        Square::new(self.file + file_step, self.rank + rank_step)
+    }
+
+    /// Parses a square in algebraic notation, such as `e4`.
+    fn parse(s: &str) -> Option<Square> {
+        let mut chars = s.chars();
+        let file = chars.next()?;
+        let rank = chars.next()?;
+        if chars.next().is_some() || !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+            return None;
+        }
+        Square::new(file as i8 - 'a' as i8, rank as i8 - '1' as i8)
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            (b'a' + self.file as u8) as char,
+            (b'1' + self.rank as u8) as char
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+struct Move {
+    from: Square,
+    to: Square,
+    promotion: Option<PieceKind>,
+}
+
+impl Move {
+    /// Parses a move in UCI long algebraic notation, such as `e2e4` or `e7e8q`.
+    fn parse(s: &str) -> Option<Move> {
+        if !s.is_ascii() || !(4..=5).contains(&s.len()) {
+            return None;
+        }
+        let promotion = match s[4..].chars().next() {
+            None => None,
+            Some(c) => Some(
+                PieceKind::from_char(c)
+                    .filter(|kind| PROMOTIONS.contains(kind) && c.is_ascii_lowercase())?,
+            ),
+        };
+        Some(Move {
+            from: Square::parse(&s[0..2])?,
+            to: Square::parse(&s[2..4])?,
+            promotion,
+        })
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+        if let Some(kind) = self.promotion {
+            write!(f, "{}", kind.to_char())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+struct CastlingRights {
+    kingside: bool,
+    queenside: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+struct Position {
+    board: [[Option<Piece>; 8]; 8], // indexed by rank, then file, starting from a1
+    to_move: Color,
+    castling: [CastlingRights; 2], // indexed by Color::index
+    // Only set after a double pawn push that an enemy pawn is next to, so that positions that
+    // differ in a capture that cannot happen still count as repetitions
+    en_passant: Option<Square>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+}
+
+impl Position {
+    fn from_fen(fen: &str) -> Result<Position, String> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if !(4..=6).contains(&fields.len()) {
+            return Err("fen must have between 4 and 6 fields".to_string());
+        }
+
+        let mut board = [[None; 8]; 8];
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err("fen must describe 8 ranks".to_string());
+        }
+        for (i, rank) in ranks.iter().enumerate() {
+            let rank_index = 7 - i;
+            let mut file = 0;
+            for c in rank.chars() {
+                if let Some(empty) = c.to_digit(10).filter(|x| (1..=8).contains(x)) {
+                    file += empty as usize;
+                } else if let Some(piece) = Piece::from_fen_char(c) {
+                    if file < 8 {
+                        board[rank_index][file] = Some(piece);
+                    }
+                    file += 1;
+                } else {
+                    return Err(format!("fen contains an invalid piece '{}'", c));
+                }
+            }
+            if file != 8 {
+                return Err(format!(
+                    "rank {} in fen does not have 8 squares",
+                    rank_index + 1
+                ));
+            }
+        }
+
+        let to_move = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => return Err("fen must have 'w' or 'b' as the side to move".to_string()),
+        };
+
+        let mut castling = [CastlingRights {
+            kingside: false,
+            queenside: false,
+        }; 2];
+        if fields[2] != "-" {
+            for c in fields[2].chars() {
+                let (color, rights) = match c {
+                    'K' => (Color::White, &mut castling[0].kingside),
+                    'Q' => (Color::White, &mut castling[0].queenside),
+                    'k' => (Color::Black, &mut castling[1].kingside),
+                    'q' => (Color::Black, &mut castling[1].queenside),
+                    _ => return Err(format!("fen contains invalid castling rights '{}'", c)),
+                };
+                *rights = true;
+
+                let rook_file = if c.eq_ignore_ascii_case(&'k') { 7 } else { 0 };
+                let rank = color.back_rank() as usize;
+                if board[rank][4]
+                    != Some(Piece {
+                        color,
+                        kind: PieceKind::King,
+                    })
+                    || board[rank][rook_file]
+                        != Some(Piece {
+                            color,
+                            kind: PieceKind::Rook,
+                        })
+                {
+                    return Err(format!(
+                        "castling rights '{}' need the king and rook on their starting squares",
+                        c
+                    ));
+                }
+            }
+        }
+
+        let en_passant = match fields[3] {
+            "-" => None,
+            square => {
+                let expected_rank = match to_move {
+                    Color::White => 5,
+                    Color::Black => 2,
+                };
+                Some(
+                    Square::parse(square)
+                        .filter(|x| x.rank == expected_rank)
+                        .ok_or_else(|| {
+                            format!("fen contains an invalid en passant square '{}'", square)
+                        })?,
+                )
+            }
+        };
+
+        let parse_number = |i: usize, default: u32| {
+            fields.get(i).map_or(Ok(default), |x| {
+                x.parse::<u32>()
+                    .map_err(|_| format!("fen contains an invalid move number '{}'", x))
+            })
+        };
+        let halfmove_clock = parse_number(4, 0)?;
+        let fullmove_number = parse_number(5, 1)?.max(1);
+
+        let position = Position {
+            board,
+            to_move,
+            castling,
+            en_passant,
+            halfmove_clock,
+            fullmove_number,
+        };
+
+        for color in [Color::White, Color::Black] {
+            let kings = board
+                .iter()
+                .flatten()
+                .filter(|&&x| {
+                    x == Some(Piece {
+                        color,
+                        kind: PieceKind::King,
+                    })
+                })
+                .count();
+            if kings != 1 {
+                return Err("fen must have exactly one king of each color".to_string());
+            }
+        }
+        if board[0]
+            .iter()
+            .chain(board[7].iter())
+            .any(|x| x.is_some_and(|piece| piece.kind == PieceKind::Pawn))
+        {
+            return Err("fen cannot have pawns on the first or last rank".to_string());
+        }
+        if position.in_check(to_move.opponent()) {
+            return Err("fen cannot have the side that just moved in check".to_string());
+        }
+
+        Ok(position)
+    }
+
+    fn to_fen(self) -> String {
+        let castling = [
+            (self.castling[0].kingside, 'K'),
+            (self.castling[0].queenside, 'Q'),
+            (self.castling[1].kingside, 'k'),
+            (self.castling[1].queenside, 'q'),
+        ]
+        .iter()
+        .filter(|(allowed, _)| *allowed)
+        .map(|(_, c)| *c)
+        .collect::<String>();
+
+        format!(
+            "{} {} {} {} {} {}",
+            self.placement(),
+            if self.to_move == Color::White {
+                "w"
+            } else {
+                "b"
+            },
+            if castling.is_empty() { "-" } else { &castling },
+            self.en_passant
+                .map_or("-".to_string(), |square| square.to_string()),
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+
+    fn placement(&self) -> String {
+        let ranks: Vec<String> = self
+            .board
+            .iter()
+            .rev()
+            .map(|rank| {
+                let mut s = String::new();
+                let mut empty = 0;
+                for square in rank {
+                    match square {
+                        None => empty += 1,
+                        Some(piece) => {
+                            if empty > 0 {
+                                s.push_str(&empty.to_string());
+                                empty = 0;
+                            }
+                            s.push(piece.to_fen_char());
+                        }
+                    }
+                }
+                if empty > 0 {
+                    s.push_str(&empty.to_string());
+                }
+                s
+            })
+            .collect();
+        ranks.join("/")
+    }
+
+    /// The part of the FEN that must match for two positions to count as a repetition.
+    fn repetition_key(&self) -> String {
+        self.to_fen()
+            .split(' ')
+            .take(4)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn piece_at(&self, square: Square) -> Option<Piece> {
+        self.board[square.rank as usize][square.file as usize]
+    }
+
+    fn set_piece(&mut self, square: Square, piece: Option<Piece>) {
+        self.board[square.rank as usize][square.file as usize] = piece;
+    }
+
+    fn squares() -> impl Iterator<Item = Square> {
+        (0..8).flat_map(|rank| (0..8).map(move |file| Square { file, rank }))
+    }
+
+    fn has_piece(&self, square: Option<Square>, color: Color, kinds: &[PieceKind]) -> bool {
+        square
+            .and_then(|x| self.piece_at(x))
+            .is_some_and(|piece| piece.color == color && kinds.contains(&piece.kind))
+    }
+
+    /// Whether any piece of color `by` attacks the given square.
+    fn is_attacked(&self, square: Square, by: Color) -> bool {
+        let pawn_attack = [-1, 1].iter().any(|&file_step| {
+            self.has_piece(
+                square.offset(file_step, -by.pawn_direction()),
+                by,
+                &[PieceKind::Pawn],
+            )
+        });
+        let step_attack = |steps: &[(i8, i8)], kind: PieceKind| {
+            steps.iter().any(|&(file_step, rank_step)| {
+                self.has_piece(square.offset(file_step, rank_step), by, &[kind])
+            })
+        };
+        let slide_attack = |directions: &[(i8, i8)], kinds: &[PieceKind]| {
+            directions.iter().any(|&(file_step, rank_step)| {
+                let mut current = square.offset(file_step, rank_step);
+                while let Some(x) = current {
+                    if self.piece_at(x).is_some() {
+                        return self.has_piece(current, by, kinds);
+                    }
+                    current = x.offset(file_step, rank_step);
+                }
+                false
+            })
+        };
+
+        pawn_attack
+            || step_attack(&KNIGHT_STEPS, PieceKind::Knight)
+            || step_attack(&KING_STEPS, PieceKind::King)
+            || slide_attack(&ROOK_DIRECTIONS, &[PieceKind::Rook, PieceKind::Queen])
+            || slide_attack(&BISHOP_DIRECTIONS, &[PieceKind::Bishop, PieceKind::Queen])
+    }
+
+    fn in_check(&self, color: Color) -> bool {
+        let king = Piece {
+            color,
+            kind: PieceKind::King,
+        };
+        Position::squares()
+            .find(|&x| self.piece_at(x) == Some(king))
+            .is_some_and(|x| self.is_attacked(x, color.opponent()))
+    }
+
+    /// Moves that follow the movement rules of the pieces, but may leave the king in check.
+    fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let color = self.to_move;
+        let mut moves = vec![];
+
+        for from in Position::squares() {
+            let piece = match self.piece_at(from) {
+                Some(piece) if piece.color == color => piece,
+                _ => continue,
+            };
+            let is_target = |to: Square| self.piece_at(to).is_none_or(|x| x.color != color);
+            let mut add = |to: Square, promotion: Option<PieceKind>| {
+                moves.push(Move {
+                    from,
+                    to,
+                    promotion,
+                })
+            };
+
+            match piece.kind {
+                PieceKind::Pawn => {
+                    let direction = color.pawn_direction();
+                    let mut targets = vec![];
+
+                    if let Some(one) = from
+                        .offset(0, direction)
+                        .filter(|&x| self.piece_at(x).is_none())
+                    {
+                        targets.push(one);
+                        let start_rank = color.back_rank() + direction;
+                        if let Some(two) = one
+                            .offset(0, direction)
+                            .filter(|&x| from.rank == start_rank && self.piece_at(x).is_none())
+                        {
+                            targets.push(two);
+                        }
+                    }
+                    for file_step in [-1, 1] {
+                        if let Some(to) = from.offset(file_step, direction).filter(|&x| {
+                            self.piece_at(x).is_some_and(|x| x.color != color)
+                                || self.en_passant == Some(x)
+                        }) {
+                            targets.push(to);
+                        }
+                    }
+
+                    for to in targets {
+                        if to.rank == color.opponent().back_rank() {
+                            for kind in PROMOTIONS {
+                                add(to, Some(kind));
+                            }
+                        } else {
+                            add(to, None);
+                        }
+                    }
+                }
+                PieceKind::Knight | PieceKind::King => {
+                    let steps = if piece.kind == PieceKind::Knight {
+                        &KNIGHT_STEPS
+                    } else {
+                        &KING_STEPS
+                    };
+                    steps
+                        .iter()
+                        .filter_map(|&(file_step, rank_step)| from.offset(file_step, rank_step))
+                        .filter(|&to| is_target(to))
+                        .for_each(|to| add(to, None));
+                }
+                PieceKind::Bishop | PieceKind::Rook | PieceKind::Queen => {
+                    let directions = match piece.kind {
+                        PieceKind::Bishop => BISHOP_DIRECTIONS.iter().collect::<Vec<_>>(),
+                        PieceKind::Rook => ROOK_DIRECTIONS.iter().collect(),
+                        _ => ROOK_DIRECTIONS
+                            .iter()
+                            .chain(BISHOP_DIRECTIONS.iter())
+                            .collect(),
+                    };
+                    for &(file_step, rank_step) in directions {
+                        let mut current = from.offset(file_step, rank_step);
+                        while let Some(to) = current {
+                            if is_target(to) {
+                                add(to, None);
+                            }
+                            if self.piece_at(to).is_some() {
+                                break;
+                            }
+                            current = to.offset(file_step, rank_step);
+                        }
+                    }
+                }
+            }
+        }
+
+        moves.extend(self.castling_moves());
+        moves
+    }
+
+    fn castling_moves(&self) -> Vec<Move> {
+        let color = self.to_move;
+        let rights = self.castling[color.index()];
+        let rank = color.back_rank();
+        let king = Square { file: 4, rank };
+        let mut moves = vec![];
+
+        if self.is_attacked(king, color.opponent()) {
+            return moves;
+        }
+
+        // The squares that must be empty, and the squares the king passes that cannot be attacked
+        let sides: [(bool, &[i8], [i8; 2]); 2] = [
+            (rights.kingside, &[5, 6], [5, 6]),
+            (rights.queenside, &[1, 2, 3], [3, 2]),
+        ];
+        for (allowed, empty, passed) in sides {
+            if allowed
+                && empty
+                    .iter()
+                    .all(|&file| self.board[rank as usize][file as usize].is_none())
+                && passed
+                    .iter()
+                    .all(|&file| !self.is_attacked(Square { file, rank }, color.opponent()))
+            {
+                moves.push(Move {
+                    from: king,
+                    to: Square {
+                        file: passed[1],
+                        rank,
+                    },
+                    promotion: None,
+                });
+            }
+        }
+
+        moves
+    }
+
+    fn legal_moves(&self) -> Vec<Move> {
+        self.pseudo_legal_moves()
+            .into_iter()
+            .filter(|&x| !self.apply(x).in_check(self.to_move))
+            .collect()
+    }
+
+    /// Returns the position after the given move, which must be pseudo-legal.
+    fn apply(&self, game_move: Move) -> Position {
+        let Move {
+            from,
+            to,
+            promotion,
+        } = game_move;
+        let color = self.to_move;
+        let mut next = *self;
+        let piece = self.piece_at(from).expect("a move starts on a piece");
+        let mut captured = self.piece_at(to).is_some();
+
+        next.set_piece(from, None);
+        next.set_piece(
+            to,
+            Some(Piece {
+                color,
+                kind: promotion.unwrap_or(piece.kind),
+            }),
+        );
+
+        if piece.kind == PieceKind::Pawn && Some(to) == self.en_passant && from.file != to.file {
+            next.set_piece(
+                Square {
+                    file: to.file,
+                    rank: from.rank,
+                },
+                None,
+            );
+            captured = true;
+        }
+
+        if piece.kind == PieceKind::King && (to.file - from.file).abs() == 2 {
+            let (rook_from, rook_to) = if to.file > from.file { (7, 5) } else { (0, 3) };
+            let rook = next.board[from.rank as usize][rook_from];
+            next.board[from.rank as usize][rook_from] = None;
+            next.board[from.rank as usize][rook_to] = rook;
+        }
+
+        if piece.kind == PieceKind::King {
+            next.castling[color.index()] = CastlingRights {
+                kingside: false,
+                queenside: false,
+            };
+        }
+        // Moving from or capturing on a corner loses the right to castle with that rook
+        for square in [from, to] {
+            for side_color in [Color::White, Color::Black] {
+                if square.rank == side_color.back_rank() {
+                    let rights = &mut next.castling[side_color.index()];
+                    match square.file {
+                        0 => rights.queenside = false,
+                        7 => rights.kingside = false,
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        next.en_passant = None;
+        if piece.kind == PieceKind::Pawn && (to.rank - from.rank).abs() == 2 {
+            let enemy_pawn_beside = [-1, 1].iter().any(|&file_step| {
+                self.has_piece(
+                    to.offset(file_step, 0),
+                    color.opponent(),
+                    &[PieceKind::Pawn],
+                )
+            });
+            if enemy_pawn_beside {
+                next.en_passant = from.offset(0, color.pawn_direction());
+            }
+        }
+
+        next.halfmove_clock = if piece.kind == PieceKind::Pawn || captured {
+            0
+        } else {
+            self.halfmove_clock + 1
+        };
+        if color == Color::Black {
+            next.fullmove_number += 1;
+        }
+        next.to_move = color.opponent();
+
+        next
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Chess {
+    game_id: GameId,
+    position: Position,
+    // Repetition keys of the positions since the last capture or pawn move, including the current
+    // one, as no earlier position can be repeated
+    repetitions: Vec<String>,
+    last_move: Option<Move>,
+    outcome: Option<Outcome>,
+    options: ChessOptions,
+}
+
+impl GameAdapter for ChessAdapter {
+    fn new(game_id: GameId, options: Value) -> actix_web::Result<Self>
+    where
+        Self: Sized,
+    {
+        let options = serde_json::from_value::<Option<ChessOptions>>(options)
+            .map_err(|e| e.to_string())
+            .and_then(|options| {
+                let options = options.unwrap_or_default();
+                options.validate()?;
+                Ok(options)
+            })
+            .map_err(|e| {
+                GameAdapterError::actix_err(game_id, GameAdapterErrorType::InvalidOptions(e))
+            })?;
+
+        Ok(ChessAdapter {
+            game_id,
+            players: vec![],
+            stage: Stage::Waiting,
+            notifier: Notifier::new(),
+            game: Chess::new(game_id, options),
+            winner: vec![],
+        })
+    }
+
+    fn load_state(state: Value) -> actix_web::Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(serde_json::from_value(state)?)
+    }
+
+    fn save_state(&self) -> actix_web::Result<Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn get_notifier(&self) -> &Notifier {
+        &self.notifier
+    }
+
+    fn add_player(&mut self, username: String) -> actix_web::Result<()> {
+        assert!(self.players.len() < NUM_PLAYERS);
+        assert_eq!(self.stage, Stage::Waiting);
+
+        self.players.push(username);
+        if self.players.len() == NUM_PLAYERS {
+            self.stage = Stage::InProgress;
+        }
+        self.notifier.send();
+        Ok(())
+    }
+
+    fn has_player(&self, username: &str) -> bool {
+        self.players.iter().any(|s| s.eq(username))
+    }
+
+    fn play_move(&mut self, game_move: GenericGameMove) -> actix_web::Result<()> {
+        if self.stage != Stage::InProgress {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidGameStage(self.stage),
+            ));
+        }
+
+        let request_payload = serde_json::from_value::<ChessRequestPayload>(game_move.payload)
+            .map_err(|e| {
+                InternalError::from_response(
+                    "",
+                    HttpResponse::BadRequest()
+                        .content_type("text/plain")
+                        .body(JsonPayloadError::Deserialize(e).to_string()),
+                )
+            })?;
+
+        let color = self.game.position.to_move;
+        if self.players[color.index()] != game_move.player {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidPlayer(game_move.player),
+            ));
+        }
+
+        self.game.play(&request_payload.uci_move)?;
+
+        if let Some(outcome) = self.game.outcome {
+            if outcome == Outcome::Checkmate {
+                self.winner.push(game_move.player);
+            }
+            self.stage = Stage::Ended;
+        }
+        self.notifier.send();
+        Ok(())
+    }
+
+    fn handle_timeout(&mut self) -> actix_web::Result<()> {
+        if self.stage != Stage::InProgress {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidGameStage(self.stage),
+            ));
+        }
+
+        // The player who ran out of time forfeits
+        let color = self.game.position.to_move.opponent();
+        self.winner = vec![self.players[color.index()].clone()];
+        self.stage = Stage::Ended;
+        self.notifier.send();
+        Ok(())
+    }
+
+    fn end_game(&mut self, winners: Vec<String>) {
+        self.winner = winners;
+        self.stage = Stage::Ended;
+    }
+
+    fn get_stage(&self) -> Stage {
+        self.stage
+    }
+
+    fn get_encoded_state(&self) -> actix_web::Result<GenericGameState> {
+        let position = &self.game.position;
+        let in_progress = self.stage == Stage::InProgress;
+
+        let response_payload = ChessResponsePayload {
+            fen: position.to_fen(),
+            legal_moves: if in_progress {
+                position
+                    .legal_moves()
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect()
+            } else {
+                vec![]
+            },
+            check: position.in_check(position.to_move),
+            last_move: self.game.last_move.map(|x| x.to_string()),
+            outcome: self.game.outcome,
+            options: &self.game.options,
+        };
+
+        Ok(GenericGameState {
+            players: self.players.clone(),
+            stage: self.stage,
+            can_move: if in_progress {
+                vec![self.players[position.to_move.index()].clone()]
+            } else {
+                vec![]
+            },
+            winners: self.winner.clone(),
+            deadline: None,
+            termination: None,
+            draw_offers: vec![],
+            payload: serde_json::to_value(&response_payload)?,
+        })
+    }
+}
+
+impl Chess {
+    fn new(game_id: GameId, options: ChessOptions) -> Self {
+        let position = Position::from_fen(&options.fen).expect("options have been validated");
+
+        Chess {
+            game_id,
+            position,
+            repetitions: vec![position.repetition_key()],
+            last_move: None,
+            outcome: None,
+            options,
+        }
+    }
+
+    /// Plays a move in UCI notation for the side to move and checks whether it ends the game.
+    fn play(&mut self, uci_move: &str) -> actix_web::Result<()> {
+        let invalid_move = |reason: String| {
+            GameAdapterError::actix_err(self.game_id, GameAdapterErrorType::InvalidMove(reason))
+        };
+
+        let game_move = Move::parse(uci_move)
+            .ok_or_else(|| invalid_move(format!("'{}' is not a move in UCI notation", uci_move)))?;
+        let legal_moves = self.position.legal_moves();
+        if !legal_moves.contains(&game_move) {
+            return Err(invalid_move(format!("{} is not a legal move", game_move)));
+        }
+
+        self.position = self.position.apply(game_move);
+        self.last_move = Some(game_move);
+
+        let key = self.position.repetition_key();
+        if self.position.halfmove_clock == 0 {
+            self.repetitions.clear();
+        }
+        self.repetitions.push(key.clone());
+
+        self.outcome = if self.position.legal_moves().is_empty() {
+            if self.position.in_check(self.position.to_move) {
+                Some(Outcome::Checkmate)
+            } else {
+                Some(Outcome::Stalemate)
+            }
+        } else if self.position.halfmove_clock >= FIFTY_MOVE_RULE_PLIES {
+            Some(Outcome::FiftyMoveRule)
+        } else if self.repetitions.iter().filter(|&x| *x == key).count() >= REPETITION_LIMIT {
+            Some(Outcome::ThreefoldRepetition)
+        } else {
+            None
+        };
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_game(fen: &str) -> Chess {
+        Chess::new(
+            GameId::new(),
+            ChessOptions {
+                fen: fen.to_string(),
+            },
+        )
+    }
+
+    fn play_moves(game: &mut Chess, moves: &[&str]) {
+        for game_move in moves {
+            game.play(game_move).unwrap();
+        }
+    }
+
+    fn perft(position: &Position, depth: usize) -> usize {
+        if depth == 0 {
+            return 1;
+        }
+        position
+            .legal_moves()
+            .iter()
+            .map(|&x| perft(&position.apply(x), depth - 1))
+            .sum()
+    }
+
+    #[test]
+    fn move_generation_matches_known_perft_counts() {
+        let cases = [
+            (START_FEN, 3, 8902),
+            // Castling, en passant and promotions
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                2,
+                2039,
+            ),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 3, 2812),
+            (
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                2,
+                1486,
+            ),
+        ];
+
+        for (fen, depth, nodes) in cases {
+            assert_eq!(
+                perft(&Position::from_fen(fen).unwrap(), depth),
+                nodes,
+                "{}",
+                fen
+            );
+        }
+    }
+
+    #[test]
+    fn fen_follows_the_moves() {
+        let mut game = create_game(START_FEN);
+        play_moves(&mut game, &["e2e4", "c7c5", "e4e5", "d7d5"]);
+
+        assert_eq!(
+            game.position.to_fen(),
+            "rnbqkbnr/pp2pppp/8/2ppP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3"
+        );
+        play_moves(&mut game, &["e5d6", "e8d7", "g1f3", "d7e8"]);
+        assert_eq!(
+            game.position.to_fen(),
+            "rnbqkbnr/pp2pppp/3P4/2p5/8/5N2/PPPP1PPP/RNBQKB1R w KQ - 3 5"
+        );
+    }
+
+    #[test]
+    fn illegal_moves_are_rejected() {
+        let mut game = create_game("r3k2r/8/8/8/8/8/5r2/R3K2R w KQkq - 0 1");
+
+        // Castling through an attacked square, moving into check and malformed moves
+        assert!(game.play("e1g1").is_err());
+        assert!(game.play("e1f1").is_err());
+        assert!(game.play("e1").is_err());
+        assert!(game.play("a1a9").is_err());
+        play_moves(&mut game, &["e1c1"]);
+        assert_eq!(
+            game.position.to_fen(),
+            "r3k2r/8/8/8/8/8/5r2/2KR3R b kq - 1 1"
+        );
+    }
+
+    #[test]
+    fn checkmate_and_stalemate_end_the_game() {
+        let mut game = create_game(START_FEN);
+        play_moves(&mut game, &["f2f3", "e7e5", "g2g4", "d8h4"]);
+        assert_eq!(game.outcome, Some(Outcome::Checkmate));
+
+        let mut game = create_game("7k/8/5K2/6Q1/8/8/8/8 w - - 0 1");
+        play_moves(&mut game, &["g5g6"]);
+        assert_eq!(game.outcome, Some(Outcome::Stalemate));
+    }
+
+    #[test]
+    fn repetitions_and_fifty_quiet_moves_draw() {
+        let mut game = create_game(START_FEN);
+        play_moves(
+            &mut game,
+            &["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1"],
+        );
+        assert_eq!(game.outcome, None);
+        play_moves(&mut game, &["f6g8"]);
+        assert_eq!(game.outcome, Some(Outcome::ThreefoldRepetition));
+
+        let mut game = create_game("4k3/8/8/8/8/8/4P3/4K2R w K - 99 80");
+        play_moves(&mut game, &["h1h2"]);
+        assert_eq!(game.outcome, Some(Outcome::FiftyMoveRule));
+    }
+}
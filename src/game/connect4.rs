@@ -1,74 +1,206 @@
 use crate::game::adapter::{
     GameAdapter, GameAdapterError, GameAdapterErrorType, GenericGameMove, GenericGameState, Stage,
 };
-use crate::game::{GameId, GameType};
+use crate::game::lines;
+use crate::game::GameId;
 use crate::notify::Notifier;
 use actix_web::error::{InternalError, JsonPayloadError};
 use actix_web::HttpResponse;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::vec;
 use std::vec::Vec;
 
-const NUM_PLAYERS: usize = 2;
-const ROW_SIZE: usize = 6;
-const COL_SIZE: usize = 7;
-const CONNECT_FOUR: usize = 4;
+pub const NUM_PLAYERS: usize = 2;
+const DEFAULT_ROWS: usize = 6;
+const DEFAULT_COLUMNS: usize = 7;
+const DEFAULT_CONNECT: usize = 4;
+const MAX_BOARD_SIZE: usize = 20;
+const MIN_CONNECT: usize = 2;
 
+#[derive(Serialize, Deserialize)]
 pub struct Connect4Adapter {
     game_id: GameId,
     players: Vec<String>,
     stage: Stage,
+    #[serde(skip, default = "Notifier::new")]
     notifier: Notifier,
     game: Connect4,
     winner: Vec<String>,
 }
 
 #[derive(Deserialize)]
-pub enum ConstConnect4 {
-    #[serde(rename = "connect_4")]
-    Connect4,
+struct Connect4RequestPayload {
+    column: usize,
 }
 
+// Payloads with an `action` are takeback requests rather than tokens dropped into a column
 #[derive(Deserialize)]
-struct Connect4RequestPayload {
-    game_type: ConstConnect4,
-    column: usize,
+struct Connect4ActionPayload {
+    action: Connect4Action,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Connect4Action {
+    ProposeTakeback,
+    AcceptTakeback,
 }
 
 #[derive(Serialize)]
 struct Connect4ResponsePayload<'a> {
     cells: Vec<Vec<&'a String>>,
+    options: &'a Connect4Options,
+    takeback_proposed_by: Option<&'a String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+struct Connect4Options {
+    rows: usize,
+    columns: usize,
+    connect: usize,
+}
+
+impl Default for Connect4Options {
+    fn default() -> Self {
+        Connect4Options {
+            rows: DEFAULT_ROWS,
+            columns: DEFAULT_COLUMNS,
+            connect: DEFAULT_CONNECT,
+        }
+    }
 }
 
+impl Connect4Options {
+    fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_BOARD_SIZE).contains(&self.rows) {
+            Err(format!("rows must be between 1 and {}", MAX_BOARD_SIZE))
+        } else if !(1..=MAX_BOARD_SIZE).contains(&self.columns) {
+            Err(format!("columns must be between 1 and {}", MAX_BOARD_SIZE))
+        } else if self.connect < MIN_CONNECT || self.connect > self.rows.max(self.columns) {
+            Err(format!(
+                "connect must be between {} and the number of rows or columns",
+                MIN_CONNECT
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 struct Connect4 {
     game_id: GameId,
     completed: bool,
     turn: Token,
     board: Vec<Vec<Token>>, // vector of columns, each variable length.
+    #[serde(default)]
+    options: Connect4Options,
+    #[serde(default)]
+    played: Vec<usize>, // columns in the order tokens were dropped into them
+    #[serde(default)]
+    takeback: Option<Token>, // player who proposed taking back the last move
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 enum Token {
     Red,
     Blue,
 }
 
+impl Token {
+    fn other(self) -> Token {
+        match self {
+            Token::Red => Token::Blue,
+            Token::Blue => Token::Red,
+        }
+    }
+}
+
 impl Connect4Adapter {
     fn get_user_from_token(&self) -> String {
         let user = match self.game.turn {
-            Token::Red => self.players.get(0).unwrap().clone(),
+            Token::Red => self.players.first().unwrap().clone(),
             Token::Blue => self.players.get(1).unwrap().clone(),
         };
         user
     }
+
+    fn get_player(&self, token: Token) -> &String {
+        match token {
+            Token::Red => self.players.first(),
+            Token::Blue => self.players.get(1),
+        }
+        .unwrap()
+    }
+
+    fn play_action(&mut self, user: String, action: Connect4Action) -> actix_web::Result<()> {
+        let token = if self.get_user_from_token() == user {
+            self.game.turn
+        } else {
+            self.game.turn.other()
+        };
+        match action {
+            Connect4Action::ProposeTakeback => {
+                // Only the last move can be taken back, so only the player who made it can ask
+                if token == self.game.turn || self.game.played.is_empty() {
+                    return Err(GameAdapterError::actix_err(
+                        self.game_id,
+                        GameAdapterErrorType::InvalidMove(String::from(
+                            "only the player who made the last move can propose taking it back",
+                        )),
+                    ));
+                }
+                if self.game.takeback.is_some() {
+                    return Err(GameAdapterError::actix_err(
+                        self.game_id,
+                        GameAdapterErrorType::InvalidMove(String::from(
+                            "a takeback has already been proposed",
+                        )),
+                    ));
+                }
+                self.game.takeback = Some(token);
+            }
+            Connect4Action::AcceptTakeback => {
+                if self.game.takeback.is_none() {
+                    return Err(GameAdapterError::actix_err(
+                        self.game_id,
+                        GameAdapterErrorType::InvalidMove(String::from(
+                            "no takeback has been proposed",
+                        )),
+                    ));
+                }
+                if self.game.takeback == Some(token) {
+                    return Err(GameAdapterError::actix_err(
+                        self.game_id,
+                        GameAdapterErrorType::InvalidPlayer(user),
+                    ));
+                }
+                self.game.take_back();
+            }
+        }
+        Ok(())
+    }
 }
 
 impl GameAdapter for Connect4Adapter {
-    fn new(game_id: GameId) -> Self
+    fn new(game_id: GameId, options: Value) -> actix_web::Result<Self>
     where
         Self: Sized,
     {
-        Connect4Adapter {
+        let options = serde_json::from_value::<Option<Connect4Options>>(options)
+            .map_err(|e| e.to_string())
+            .and_then(|options| {
+                let options = options.unwrap_or_default();
+                options.validate()?;
+                Ok(options)
+            })
+            .map_err(|e| {
+                GameAdapterError::actix_err(game_id, GameAdapterErrorType::InvalidOptions(e))
+            })?;
+
+        Ok(Connect4Adapter {
             game_id,
             players: vec![],
             stage: Stage::Waiting,
@@ -77,10 +209,24 @@ impl GameAdapter for Connect4Adapter {
                 game_id,
                 completed: false,
                 turn: Token::Red,
-                board: vec![vec![]; COL_SIZE],
+                board: vec![vec![]; options.columns],
+                options,
+                played: vec![],
+                takeback: None,
             },
             winner: vec![],
-        }
+        })
+    }
+
+    fn load_state(state: Value) -> actix_web::Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(serde_json::from_value(state)?)
+    }
+
+    fn save_state(&self) -> actix_web::Result<Value> {
+        Ok(serde_json::to_value(self)?)
     }
 
     fn get_notifier(&self) -> &Notifier {
@@ -117,15 +263,24 @@ impl GameAdapter for Connect4Adapter {
             ));
         }
 
-        let request_payload = serde_json::from_value::<Connect4RequestPayload>(game_move.payload)
-            .map_err(|e| {
+        let to_bad_request = |e| {
             InternalError::from_response(
                 "",
                 HttpResponse::BadRequest()
                     .content_type("text/plain")
                     .body(JsonPayloadError::Deserialize(e).to_string()),
             )
-        })?;
+        };
+        if game_move.payload.get("action").is_some() {
+            let action_payload = serde_json::from_value::<Connect4ActionPayload>(game_move.payload)
+                .map_err(to_bad_request)?;
+            self.play_action(game_move.player, action_payload.action)?;
+            self.notifier.send();
+            return Ok(());
+        }
+
+        let request_payload = serde_json::from_value::<Connect4RequestPayload>(game_move.payload)
+            .map_err(to_bad_request)?;
 
         let column = request_payload.column;
         let player = self.get_user_from_token();
@@ -138,6 +293,8 @@ impl GameAdapter for Connect4Adapter {
             )); // return the one who made the request
         }
         self.game.moves(column)?;
+        // Playing on declines any takeback the opponent proposed
+        self.game.takeback = None;
         let win = self.game.winning_move(column);
         let draw = self.game.is_game_drawn();
         if win {
@@ -153,6 +310,34 @@ impl GameAdapter for Connect4Adapter {
         Ok(())
     }
 
+    fn handle_timeout(&mut self) -> actix_web::Result<()> {
+        if self.stage != Stage::InProgress {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidGameStage(self.stage),
+            ));
+        }
+
+        // The player who ran out of time forfeits
+        let player = self.get_user_from_token();
+        self.winner = self
+            .players
+            .iter()
+            .filter(|&x| x != &player)
+            .cloned()
+            .collect();
+        self.game.completed = true;
+        self.stage = Stage::Ended;
+        self.notifier.send();
+        Ok(())
+    }
+
+    fn end_game(&mut self, winners: Vec<String>) {
+        self.winner = winners;
+        self.game.completed = true;
+        self.stage = Stage::Ended;
+    }
+
     fn get_stage(&self) -> Stage {
         self.stage
     }
@@ -164,19 +349,15 @@ impl GameAdapter for Connect4Adapter {
             .iter()
             .map(|col| {
                 col.iter()
-                    .map(|&token| {
-                        match token {
-                            Token::Red => self.players.get(0),
-                            Token::Blue => self.players.get(1),
-                        }
-                        .unwrap()
-                    })
+                    .map(|&token| self.get_player(token))
                     .collect::<Vec<_>>()
             })
             .collect::<Vec<_>>();
 
         let response_payload = Connect4ResponsePayload {
             cells: encoded_board,
+            options: &self.game.options,
+            takeback_proposed_by: self.game.takeback.map(|token| self.get_player(token)),
         };
         Ok(GenericGameState {
             players: self.players.clone(),
@@ -187,79 +368,66 @@ impl GameAdapter for Connect4Adapter {
                 vec![]
             },
             winners: self.winner.clone(),
+            deadline: None,
+            termination: None,
+            draw_offers: vec![],
             payload: serde_json::to_value(&response_payload)?,
         })
     }
-
-    fn get_type(&self) -> GameType {
-        GameType::Connect4
-    }
 }
 
 impl Connect4 {
     fn get_cell_at(&self, row: isize, col: isize) -> Option<Token> {
-        if row < 0 || col < 0 || row >= ROW_SIZE as isize || col >= COL_SIZE as isize {
+        if row < 0
+            || col < 0
+            || row >= self.options.rows as isize
+            || col >= self.options.columns as isize
+        {
             return None;
         }
         Some(*self.board.get(col as usize)?.get(row as usize)?)
     }
 
     fn insert_move_if_legal(&mut self, column: usize) -> actix_web::Result<()> {
-        if column >= COL_SIZE {
+        if column >= self.options.columns {
             return Err(GameAdapterError::actix_err(
                 self.game_id,
                 GameAdapterErrorType::InvalidMove(format!("column {} does not exist", column)),
             ));
-        } else if self.board.get(column).unwrap().len() >= ROW_SIZE {
+        } else if self.board.get(column).unwrap().len() >= self.options.rows {
             return Err(GameAdapterError::actix_err(
                 self.game_id,
                 GameAdapterErrorType::InvalidMove(format!("column {} is already full", column)),
             ));
         } else {
             self.board.get_mut(column).unwrap().push(self.turn);
+            self.played.push(column);
         }
         Ok(())
     }
 
     fn switch_token(&mut self) {
-        self.turn = match self.turn {
-            Token::Red => Token::Blue,
-            Token::Blue => Token::Red,
-        };
+        self.turn = self.turn.other();
     }
-    fn winning_move(&mut self, column: usize) -> bool {
-        if column >= COL_SIZE {
-            return false;
-        }
-        let row = self.board.get(column).unwrap().len() - 1;
-        let direction_col = vec![0, -1, 1, -1, 1, -1, 1]; // Down,Left,Right,LU ,RD, LD, RU
-        let direction_row = vec![-1, 0, 0, 1, -1, -1, 1];
-        let mut lengths = Vec::with_capacity(7);
-        let mut col_parser;
-        let mut row_parser;
-        lengths.resize(7, 0);
-        for counter in 0..7 {
-            col_parser = column as isize; // usize
-            row_parser = row as isize;
-            while self.get_cell_at(row_parser, col_parser) == Some(self.turn) {
-                lengths[counter] += 1;
-                col_parser += direction_col[counter];
-                row_parser += direction_row[counter];
-            }
-        }
-        if lengths[0] >= CONNECT_FOUR as isize {
-            return true;
+
+    fn take_back(&mut self) {
+        if let Some(column) = self.played.pop() {
+            self.board.get_mut(column).unwrap().pop();
+            self.switch_token();
         }
-        for pair in 0..3 {
-            if lengths[2 * pair + 1] + lengths[2 * pair + 2] > CONNECT_FOUR as isize {
-                return true;
-            }
+        self.takeback = None;
+    }
+    fn winning_move(&self, column: usize) -> bool {
+        match self.board.get(column).and_then(|x| x.len().checked_sub(1)) {
+            Some(row) => lines::in_a_row(row, column, self.options.connect, |row, column| {
+                self.get_cell_at(row, column) == Some(self.turn)
+            }),
+            None => false,
         }
-        false
     }
 
     fn is_game_drawn(&self) -> bool {
-        self.board.iter().all(|ref col| col.len() == ROW_SIZE)
+        self.board.iter().all(|col| col.len() == self.options.rows)
     }
 
     fn moves(&mut self, column: usize) -> actix_web::Result<()> {
@@ -283,11 +451,14 @@ mod tests {
             game_id: GameId::new(),
             completed: false,
             turn: Token::Red,
-            board: vec![vec![]; COL_SIZE],
+            board: vec![vec![]; DEFAULT_COLUMNS],
+            options: Connect4Options::default(),
+            played: vec![],
+            takeback: None,
         }
     }
 
-    fn display_board(board: &Vec<Vec<Token>>) {
+    fn display_board(board: &[Vec<Token>]) {
         board.iter().for_each(|it| {
             println!("{:#?}", it);
         });
@@ -317,7 +488,10 @@ mod tests {
             game_id: GameId::new(),
             completed: false,
             turn: Token::Red,
-            board: vec![vec![]; COL_SIZE],
+            board: vec![vec![]; DEFAULT_COLUMNS],
+            options: Connect4Options::default(),
+            played: vec![],
+            takeback: None,
         };
 
         play_moves(&mut game, vec![3, 3, 2, 0, 1, 1, 4]);
@@ -332,7 +506,10 @@ mod tests {
             game_id: GameId::new(),
             completed: false,
             turn: Token::Red,
-            board: vec![vec![]; COL_SIZE],
+            board: vec![vec![]; DEFAULT_COLUMNS],
+            options: Connect4Options::default(),
+            played: vec![],
+            takeback: None,
         };
 
         play_moves(&mut game, vec![2, 3, 1, 2, 1, 1, 0, 0, 0, 0]);
@@ -347,7 +524,10 @@ mod tests {
             game_id: GameId::new(),
             completed: false,
             turn: Token::Red,
-            board: vec![vec![]; COL_SIZE],
+            board: vec![vec![]; DEFAULT_COLUMNS],
+            options: Connect4Options::default(),
+            played: vec![],
+            takeback: None,
         };
 
         play_moves(&mut game, vec![2, 3, 3, 4, 4, 5, 4, 5, 0, 5, 5]);
@@ -355,4 +535,105 @@ mod tests {
         display_board(&game.board);
         assert!(game.winning_move(5));
     }
+
+    #[test]
+    fn timed_out_player_forfeits() {
+        let mut adapter = Connect4Adapter::new(GameId::new(), Value::Null).unwrap();
+        adapter.add_player(String::from("a")).unwrap();
+        adapter.add_player(String::from("b")).unwrap();
+        adapter
+            .play_move(GenericGameMove {
+                player: String::from("a"),
+                payload: serde_json::json!({ "game_type": "connect_4", "column": 0 }),
+            })
+            .unwrap();
+
+        adapter.handle_timeout().unwrap();
+
+        let state = adapter.get_encoded_state().unwrap();
+        assert_eq!(state.stage, Stage::Ended);
+        assert_eq!(state.winners, vec![String::from("a")]);
+        assert!(adapter.handle_timeout().is_err());
+    }
+
+    #[test]
+    fn options_change_board_and_win_length() {
+        let options = serde_json::json!({ "rows": 3, "columns": 3, "connect": 3 });
+        let mut adapter = Connect4Adapter::new(GameId::new(), options).unwrap();
+
+        assert!(adapter.game.insert_move_if_legal(3).is_err());
+        play_moves(&mut adapter.game, vec![0, 1, 0, 1, 0]);
+
+        assert!(adapter.game.winning_move(0));
+        assert!(adapter.game.insert_move_if_legal(0).is_err());
+    }
+
+    #[test]
+    fn invalid_options_are_rejected() {
+        for options in [
+            serde_json::json!({ "rows": 0 }),
+            serde_json::json!({ "columns": 21 }),
+            serde_json::json!({ "connect": 8 }),
+            serde_json::json!({ "colour": "red" }),
+        ] {
+            assert!(Connect4Adapter::new(GameId::new(), options).is_err());
+        }
+    }
+
+    fn play(adapter: &mut Connect4Adapter, player: &str, payload: Value) -> actix_web::Result<()> {
+        adapter.play_move(GenericGameMove {
+            player: String::from(player),
+            payload,
+        })
+    }
+
+    #[test]
+    fn accepted_takeback_restores_board_and_turn() {
+        let mut adapter = Connect4Adapter::new(GameId::new(), Value::Null).unwrap();
+        adapter.add_player(String::from("a")).unwrap();
+        adapter.add_player(String::from("b")).unwrap();
+        play(&mut adapter, "a", serde_json::json!({ "column": 0 })).unwrap();
+        play(&mut adapter, "b", serde_json::json!({ "column": 3 })).unwrap();
+
+        let propose = serde_json::json!({ "action": "propose_takeback" });
+        let accept = serde_json::json!({ "action": "accept_takeback" });
+        play(&mut adapter, "b", propose).unwrap();
+        let state = adapter.get_encoded_state().unwrap();
+        assert_eq!(state.payload["takeback_proposed_by"], "b");
+        assert_eq!(state.can_move, vec![String::from("a")]);
+
+        play(&mut adapter, "a", accept).unwrap();
+        let state = adapter.get_encoded_state().unwrap();
+        assert_eq!(state.payload["takeback_proposed_by"], Value::Null);
+        assert_eq!(state.can_move, vec![String::from("b")]);
+        assert!(adapter.game.board[3].is_empty());
+        assert_eq!(adapter.game.board[0], vec![Token::Red]);
+        assert_eq!(adapter.game.played, vec![0]);
+    }
+
+    #[test]
+    fn takebacks_follow_proposal_rules() {
+        let mut adapter = Connect4Adapter::new(GameId::new(), Value::Null).unwrap();
+        adapter.add_player(String::from("a")).unwrap();
+        adapter.add_player(String::from("b")).unwrap();
+        let propose = serde_json::json!({ "action": "propose_takeback" });
+        let accept = serde_json::json!({ "action": "accept_takeback" });
+
+        // Nothing to take back yet, and nothing proposed to accept
+        assert!(play(&mut adapter, "b", propose.clone()).is_err());
+        assert!(play(&mut adapter, "b", accept.clone()).is_err());
+        play(&mut adapter, "a", serde_json::json!({ "column": 0 })).unwrap();
+
+        // Only the player who moved last can propose, and not twice
+        assert!(play(&mut adapter, "b", propose.clone()).is_err());
+        play(&mut adapter, "a", propose.clone()).unwrap();
+        assert!(play(&mut adapter, "a", propose).is_err());
+        assert!(play(&mut adapter, "a", accept.clone()).is_err());
+
+        // Playing on declines the proposal
+        play(&mut adapter, "b", serde_json::json!({ "column": 1 })).unwrap();
+        assert!(adapter.game.takeback.is_none());
+        assert!(play(&mut adapter, "a", accept).is_err());
+        assert!(play(&mut adapter, "a", serde_json::json!({ "action": "undo" })).is_err());
+    }
 }
@@ -0,0 +1,838 @@
+use crate::game::adapter::{
+    GameAdapter, GameAdapterError, GameAdapterErrorType, GenericGameMove, GenericGameState, Stage,
+};
+use crate::game::GameId;
+use crate::notify::Notifier;
+use actix_web::error::{InternalError, JsonPayloadError};
+use actix_web::HttpResponse;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::vec;
+use std::vec::Vec;
+
+pub const NUM_PLAYERS: usize = 4;
+const CARDS_PER_HAND: usize = 52 / NUM_PLAYERS;
+const CARDS_PASSED: usize = 3;
+const DEFAULT_TARGET_SCORE: u32 = 100;
+const MAX_TARGET_SCORE: u32 = 1000;
+// Every heart is worth a point and the queen of spades is worth 13
+const TOTAL_POINTS: u32 = 26;
+const RANKS: &str = "23456789TJQKA";
+const TWO_OF_CLUBS: Card = Card {
+    suit: Suit::Clubs,
+    rank: 2,
+};
+const QUEEN_OF_SPADES: Card = Card {
+    suit: Suit::Spades,
+    rank: 12,
+};
+
+#[derive(Serialize, Deserialize)]
+pub struct HeartsAdapter {
+    game_id: GameId,
+    players: Vec<String>,
+    stage: Stage,
+    #[serde(skip, default = "Notifier::new")]
+    notifier: Notifier,
+    game: Hearts,
+    winner: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct HeartsRequestPayload {
+    #[serde(flatten)]
+    action: HeartsAction,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum HeartsAction {
+    Pass { cards: Vec<Card> },
+    Play { card: Card },
+}
+
+#[derive(Serialize)]
+struct PlayedCardPayload<'a> {
+    player: &'a String,
+    card: Card,
+}
+
+#[derive(Serialize)]
+struct LastTrickPayload<'a> {
+    cards: Vec<PlayedCardPayload<'a>>,
+    winner: &'a String,
+}
+
+#[derive(Serialize)]
+struct HeartsResponsePayload<'a> {
+    hand: Option<&'a Vec<Card>>,
+    legal_cards: Vec<Card>,
+    hand_sizes: HashMap<&'a String, usize>,
+    phase: Phase,
+    pass_direction: PassDirection,
+    trick: Vec<PlayedCardPayload<'a>>,
+    last_trick: Option<LastTrickPayload<'a>>,
+    hearts_broken: bool,
+    hand_points: HashMap<&'a String, u32>,
+    scores: HashMap<&'a String, u32>,
+    hand_number: usize,
+    options: &'a HeartsOptions,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+struct HeartsOptions {
+    target_score: u32,
+    passing: bool,
+}
+
+impl Default for HeartsOptions {
+    fn default() -> Self {
+        HeartsOptions {
+            target_score: DEFAULT_TARGET_SCORE,
+            passing: true,
+        }
+    }
+}
+
+impl HeartsOptions {
+    fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_TARGET_SCORE).contains(&self.target_score) {
+            Err(format!(
+                "target_score must be between 1 and {}",
+                MAX_TARGET_SCORE
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Suit {
+    Clubs,
+    Diamonds,
+    Spades,
+    Hearts,
+}
+
+impl Suit {
+    const ALL: [Suit; 4] = [Suit::Clubs, Suit::Diamonds, Suit::Spades, Suit::Hearts];
+
+    fn to_char(self) -> char {
+        match self {
+            Suit::Clubs => 'C',
+            Suit::Diamonds => 'D',
+            Suit::Spades => 'S',
+            Suit::Hearts => 'H',
+        }
+    }
+}
+
+/// A playing card, written as its rank followed by its suit, such as `TH` for the ten of hearts.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(try_from = "String", into = "String")]
+struct Card {
+    suit: Suit,
+    rank: u8, // from 2 up to 14 for the ace
+}
+
+impl Card {
+    fn points(self) -> u32 {
+        if self.suit == Suit::Hearts {
+            1
+        } else if self == QUEEN_OF_SPADES {
+            13
+        } else {
+            0
+        }
+    }
+}
+
+impl TryFrom<String> for Card {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let invalid_card = || format!("'{}' is not a card", s);
+        let mut chars = s.chars();
+        let (rank, suit) = match (chars.next(), chars.next(), chars.next()) {
+            (Some(rank), Some(suit), None) => (rank, suit),
+            _ => return Err(invalid_card()),
+        };
+
+        let rank = RANKS.find(rank).ok_or_else(invalid_card)? as u8 + 2;
+        let suit = *Suit::ALL
+            .iter()
+            .find(|x| x.to_char() == suit)
+            .ok_or_else(invalid_card)?;
+        Ok(Card { suit, rank })
+    }
+}
+
+impl From<Card> for String {
+    fn from(card: Card) -> Self {
+        card.to_string()
+    }
+}
+
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rank = RANKS.as_bytes()[self.rank as usize - 2] as char;
+        write!(f, "{}{}", rank, self.suit.to_char())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum Phase {
+    Passing,
+    Playing,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum PassDirection {
+    Left,
+    Right,
+    Across,
+    None,
+}
+
+impl PassDirection {
+    /// How many seats to the left the cards are passed.
+    fn offset(self) -> usize {
+        match self {
+            PassDirection::Left => 1,
+            PassDirection::Right => NUM_PLAYERS - 1,
+            PassDirection::Across => 2,
+            PassDirection::None => 0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct PlayedCard {
+    player: usize,
+    card: Card,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Hearts {
+    game_id: GameId,
+    hands: Vec<Vec<Card>>, // indexed by player, in sorted order
+    phase: Phase,
+    // The cards each player has chosen to pass, which are exchanged once every player has chosen
+    passes: Vec<Option<Vec<Card>>>,
+    // Index of the player to play next
+    turn: usize,
+    trick: Vec<PlayedCard>,
+    last_trick: Option<Vec<PlayedCard>>,
+    tricks_played: usize,
+    hearts_broken: bool,
+    hand_points: Vec<u32>,
+    scores: Vec<u32>,
+    hand_number: usize,
+    options: HeartsOptions,
+    // Each hand is shuffled from the seed, so that replaying the same moves deals the same cards
+    #[serde(default = "random_seed")]
+    seed: u64,
+}
+
+fn random_seed() -> u64 {
+    thread_rng().gen()
+}
+
+impl GameAdapter for HeartsAdapter {
+    fn new(game_id: GameId, options: Value) -> actix_web::Result<Self>
+    where
+        Self: Sized,
+    {
+        let options = serde_json::from_value::<Option<HeartsOptions>>(options)
+            .map_err(|e| e.to_string())
+            .and_then(|options| {
+                let options = options.unwrap_or_default();
+                options.validate()?;
+                Ok(options)
+            })
+            .map_err(|e| {
+                GameAdapterError::actix_err(game_id, GameAdapterErrorType::InvalidOptions(e))
+            })?;
+
+        Ok(HeartsAdapter {
+            game_id,
+            players: vec![],
+            stage: Stage::Waiting,
+            notifier: Notifier::new(),
+            game: Hearts::new(game_id, options),
+            winner: vec![],
+        })
+    }
+
+    fn load_state(state: Value) -> actix_web::Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(serde_json::from_value(state)?)
+    }
+
+    fn save_state(&self) -> actix_web::Result<Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn get_notifier(&self) -> &Notifier {
+        &self.notifier
+    }
+
+    fn add_player(&mut self, username: String) -> actix_web::Result<()> {
+        assert!(self.players.len() < NUM_PLAYERS);
+        assert_eq!(self.stage, Stage::Waiting);
+
+        self.players.push(username);
+        if self.players.len() == NUM_PLAYERS {
+            self.stage = Stage::InProgress;
+        }
+        self.notifier.send();
+        Ok(())
+    }
+
+    fn has_player(&self, username: &str) -> bool {
+        self.players.iter().any(|s| s.eq(username))
+    }
+
+    fn play_move(&mut self, game_move: GenericGameMove) -> actix_web::Result<()> {
+        if self.stage != Stage::InProgress {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidGameStage(self.stage),
+            ));
+        }
+
+        let request_payload = serde_json::from_value::<HeartsRequestPayload>(game_move.payload)
+            .map_err(|e| {
+                InternalError::from_response(
+                    "",
+                    HttpResponse::BadRequest()
+                        .content_type("text/plain")
+                        .body(JsonPayloadError::Deserialize(e).to_string()),
+                )
+            })?;
+
+        let player = match self.players.iter().position(|x| *x == game_move.player) {
+            Some(player) if self.game.can_move().contains(&player) => player,
+            _ => {
+                return Err(GameAdapterError::actix_err(
+                    self.game_id,
+                    GameAdapterErrorType::InvalidPlayer(game_move.player),
+                ))
+            }
+        };
+
+        match request_payload.action {
+            HeartsAction::Pass { cards } => self.game.pass(player, cards)?,
+            HeartsAction::Play { card } => self.game.play(player, card)?,
+        }
+
+        self.check_game_over();
+        self.notifier.send();
+        Ok(())
+    }
+
+    fn handle_timeout(&mut self) -> actix_web::Result<()> {
+        if self.stage != Stage::InProgress {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidGameStage(self.stage),
+            ));
+        }
+
+        // Players who ran out of time pass the last cards of their sorted hand or play their first
+        // legal card, so that the other players can carry on
+        for player in self.game.can_move() {
+            match self.game.phase {
+                Phase::Passing => {
+                    let hand = &self.game.hands[player];
+                    let cards = hand[hand.len() - CARDS_PASSED..].to_vec();
+                    self.game.pass(player, cards)?;
+                }
+                Phase::Playing => {
+                    let card = self.game.legal_cards(player)[0];
+                    self.game.play(player, card)?;
+                }
+            }
+        }
+
+        self.check_game_over();
+        self.notifier.send();
+        Ok(())
+    }
+
+    fn end_game(&mut self, winners: Vec<String>) {
+        self.winner = winners;
+        self.stage = Stage::Ended;
+    }
+
+    fn get_stage(&self) -> Stage {
+        self.stage
+    }
+
+    fn get_encoded_state(&self) -> actix_web::Result<GenericGameState> {
+        self.get_viewer_state(None)
+    }
+
+    fn get_viewer_state(&self, viewer: Option<&str>) -> actix_web::Result<GenericGameState> {
+        let game = &self.game;
+        let viewer = viewer.and_then(|viewer| self.players.iter().position(|x| x == viewer));
+        let in_progress = self.stage == Stage::InProgress;
+        let can_move = if in_progress { game.can_move() } else { vec![] };
+        let by_player = |values: &[u32]| -> HashMap<&String, u32> {
+            self.players.iter().zip(values.iter().copied()).collect()
+        };
+        let played_cards = |cards: &[PlayedCard]| -> Vec<PlayedCardPayload> {
+            cards
+                .iter()
+                .map(|x| PlayedCardPayload {
+                    player: &self.players[x.player],
+                    card: x.card,
+                })
+                .collect()
+        };
+
+        let response_payload = HeartsResponsePayload {
+            hand: viewer.map(|x| &game.hands[x]),
+            legal_cards: match viewer {
+                Some(x) if can_move.contains(&x) && game.phase == Phase::Playing => {
+                    game.legal_cards(x)
+                }
+                _ => vec![],
+            },
+            hand_sizes: self
+                .players
+                .iter()
+                .zip(game.hands.iter().map(Vec::len))
+                .collect(),
+            phase: game.phase,
+            pass_direction: game.pass_direction(),
+            trick: played_cards(&game.trick),
+            last_trick: game.last_trick.as_ref().map(|x| LastTrickPayload {
+                cards: played_cards(x),
+                winner: &self.players[Hearts::trick_winner(x)],
+            }),
+            hearts_broken: game.hearts_broken,
+            hand_points: by_player(&game.hand_points),
+            scores: by_player(&game.scores),
+            hand_number: game.hand_number,
+            options: &game.options,
+        };
+
+        Ok(GenericGameState {
+            players: self.players.clone(),
+            stage: self.stage,
+            can_move: can_move.iter().map(|&x| self.players[x].clone()).collect(),
+            winners: self.winner.clone(),
+            deadline: None,
+            termination: None,
+            draw_offers: vec![],
+            payload: serde_json::to_value(&response_payload)?,
+        })
+    }
+
+    fn has_private_moves(&self) -> bool {
+        true
+    }
+}
+
+impl HeartsAdapter {
+    /// Ends the game once a player has reached the target score, with the lowest scores winning.
+    fn check_game_over(&mut self) {
+        let scores = &self.game.scores;
+        if !self.game.is_over() {
+            return;
+        }
+
+        let lowest = scores.iter().min().copied().unwrap_or_default();
+        self.winner = self
+            .players
+            .iter()
+            .zip(scores)
+            .filter(|(_, &score)| score == lowest)
+            .map(|(player, _)| player.clone())
+            .collect();
+        self.stage = Stage::Ended;
+    }
+}
+
+impl Hearts {
+    fn new(game_id: GameId, options: HeartsOptions) -> Self {
+        let mut game = Hearts {
+            game_id,
+            hands: vec![],
+            phase: Phase::Playing,
+            passes: vec![],
+            turn: 0,
+            trick: vec![],
+            last_trick: None,
+            tricks_played: 0,
+            hearts_broken: false,
+            hand_points: vec![],
+            scores: vec![0; NUM_PLAYERS],
+            hand_number: 0,
+            options,
+            seed: random_seed(),
+        };
+        game.deal();
+        game
+    }
+
+    fn pass_direction(&self) -> PassDirection {
+        if !self.options.passing {
+            return PassDirection::None;
+        }
+        match self.hand_number % 4 {
+            0 => PassDirection::Left,
+            1 => PassDirection::Right,
+            2 => PassDirection::Across,
+            _ => PassDirection::None,
+        }
+    }
+
+    /// Shuffles and deals a new hand, starting with the passing phase if there is one.
+    fn deal(&mut self) {
+        let mut deck: Vec<Card> = Suit::ALL
+            .iter()
+            .flat_map(|&suit| (2..=14).map(move |rank| Card { suit, rank }))
+            .collect();
+        deck.shuffle(&mut self.hand_rng());
+
+        self.hands = deck
+            .chunks(CARDS_PER_HAND)
+            .map(|x| {
+                let mut hand = x.to_vec();
+                hand.sort();
+                hand
+            })
+            .collect();
+        self.passes = vec![None; NUM_PLAYERS];
+        self.trick = vec![];
+        self.last_trick = None;
+        self.tricks_played = 0;
+        self.hearts_broken = false;
+        self.hand_points = vec![0; NUM_PLAYERS];
+
+        if self.pass_direction() == PassDirection::None {
+            self.start_playing();
+        } else {
+            self.phase = Phase::Passing;
+        }
+    }
+
+    fn start_playing(&mut self) {
+        self.phase = Phase::Playing;
+        self.turn = self
+            .hands
+            .iter()
+            .position(|x| x.contains(&TWO_OF_CLUBS))
+            .expect("the two of clubs has been dealt");
+    }
+
+    fn can_move(&self) -> Vec<usize> {
+        match self.phase {
+            Phase::Passing => (0..NUM_PLAYERS)
+                .filter(|&x| self.passes[x].is_none())
+                .collect(),
+            Phase::Playing => vec![self.turn],
+        }
+    }
+
+    fn invalid_move(&self, reason: &str) -> actix_web::Error {
+        GameAdapterError::actix_err(
+            self.game_id,
+            GameAdapterErrorType::InvalidMove(reason.to_string()),
+        )
+    }
+
+    fn pass(&mut self, player: usize, mut cards: Vec<Card>) -> actix_web::Result<()> {
+        if self.phase != Phase::Passing {
+            return Err(self.invalid_move("cards can only be passed before the first trick"));
+        }
+        cards.sort();
+        cards.dedup();
+        if cards.len() != CARDS_PASSED {
+            return Err(self.invalid_move("exactly 3 different cards must be passed"));
+        }
+        if !cards.iter().all(|x| self.hands[player].contains(x)) {
+            return Err(self.invalid_move("only cards in the hand can be passed"));
+        }
+
+        self.passes[player] = Some(cards);
+        if self.passes.iter().all(Option::is_some) {
+            let offset = self.pass_direction().offset();
+            for from in 0..NUM_PLAYERS {
+                let cards = self.passes[from].take().unwrap_or_default();
+                self.hands[from].retain(|x| !cards.contains(x));
+                let to = &mut self.hands[(from + offset) % NUM_PLAYERS];
+                to.extend(cards);
+                to.sort();
+            }
+            self.start_playing();
+        }
+        Ok(())
+    }
+
+    /// The cards the player may play next, which must follow the suit that was led if possible.
+    fn legal_cards(&self, player: usize) -> Vec<Card> {
+        let hand = &self.hands[player];
+        let first_trick = self.tricks_played == 0;
+        let matching = |predicate: &dyn Fn(&Card) -> bool| -> Vec<Card> {
+            hand.iter().copied().filter(|x| predicate(x)).collect()
+        };
+        // Keeps to the cards matching the predicate, unless the hand has none of them
+        let preferring = |predicate: &dyn Fn(&Card) -> bool| {
+            let cards = matching(predicate);
+            if cards.is_empty() {
+                hand.clone()
+            } else {
+                cards
+            }
+        };
+
+        match self.trick.first() {
+            None if first_trick => vec![TWO_OF_CLUBS],
+            None if !self.hearts_broken => preferring(&|x| x.suit != Suit::Hearts),
+            None => hand.clone(),
+            Some(led) => {
+                let following = matching(&|x| x.suit == led.card.suit);
+                if !following.is_empty() {
+                    following
+                } else if first_trick {
+                    // No points can be played on the first trick unless there is no other choice
+                    preferring(&|x| x.points() == 0)
+                } else {
+                    hand.clone()
+                }
+            }
+        }
+    }
+
+    fn play(&mut self, player: usize, card: Card) -> actix_web::Result<()> {
+        if self.phase != Phase::Playing {
+            return Err(self.invalid_move("cards cannot be played until every player has passed"));
+        }
+        if !self.hands[player].contains(&card) {
+            return Err(self.invalid_move(&format!("{} is not in the hand", card)));
+        }
+        if !self.legal_cards(player).contains(&card) {
+            return Err(self.invalid_move(&format!("{} cannot be played now", card)));
+        }
+
+        self.hands[player].retain(|&x| x != card);
+        self.trick.push(PlayedCard { player, card });
+        if card.suit == Suit::Hearts {
+            self.hearts_broken = true;
+        }
+
+        if self.trick.len() < NUM_PLAYERS {
+            self.turn = (self.turn + 1) % NUM_PLAYERS;
+            return Ok(());
+        }
+
+        let winner = Hearts::trick_winner(&self.trick);
+        self.hand_points[winner] += self.trick.iter().map(|x| x.card.points()).sum::<u32>();
+        self.last_trick = Some(std::mem::take(&mut self.trick));
+        self.tricks_played += 1;
+        self.turn = winner;
+
+        if self.tricks_played == CARDS_PER_HAND {
+            self.score_hand();
+        }
+        Ok(())
+    }
+
+    /// The player who played the highest card of the suit that was led.
+    fn trick_winner(trick: &[PlayedCard]) -> usize {
+        let led = trick[0].card.suit;
+        trick
+            .iter()
+            .filter(|x| x.card.suit == led)
+            .max_by_key(|x| x.card.rank)
+            .map(|x| x.player)
+            .expect("a trick has been led")
+    }
+
+    /// A generator for the current hand, derived from the seed and the hand number.
+    fn hand_rng(&self) -> StdRng {
+        let mut seed = <StdRng as SeedableRng>::Seed::default();
+        seed[..8].copy_from_slice(&self.seed.to_le_bytes());
+        seed[8..16].copy_from_slice(&(self.hand_number as u64).to_le_bytes());
+        StdRng::from_seed(seed)
+    }
+
+    /// Adds the points taken in the hand to the scores and deals the next hand, unless the game
+    /// is over. A player who takes every point shoots the moon, giving the points to everyone else.
+    fn score_hand(&mut self) {
+        let shooter = self.hand_points.iter().position(|&x| x == TOTAL_POINTS);
+        for player in 0..NUM_PLAYERS {
+            self.scores[player] += match shooter {
+                Some(shooter) if shooter == player => 0,
+                Some(_) => TOTAL_POINTS,
+                None => self.hand_points[player],
+            };
+        }
+
+        if !self.is_over() {
+            self.hand_number += 1;
+            self.deal();
+        }
+    }
+
+    fn is_over(&self) -> bool {
+        self.scores.iter().any(|&x| x >= self.options.target_score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(s: &str) -> Card {
+        Card::try_from(s.to_string()).unwrap()
+    }
+
+    fn cards(s: &str) -> Vec<Card> {
+        let mut cards: Vec<Card> = s.split(' ').map(card).collect();
+        cards.sort();
+        cards
+    }
+
+    fn create_game(hands: [&str; NUM_PLAYERS]) -> Hearts {
+        let mut game = Hearts::new(
+            GameId::new(),
+            HeartsOptions {
+                target_score: DEFAULT_TARGET_SCORE,
+                passing: false,
+            },
+        );
+        game.hands = hands.iter().map(|x| cards(x)).collect();
+        game.start_playing();
+        game
+    }
+
+    #[test]
+    fn cards_are_written_as_rank_and_suit() {
+        assert_eq!(card("2C"), TWO_OF_CLUBS);
+        assert_eq!(card("QS"), QUEEN_OF_SPADES);
+        assert_eq!(card("TH").to_string(), "TH");
+        assert_eq!(card("AD").rank, 14);
+        for s in ["1C", "10H", "QX", "qs", ""] {
+            assert!(Card::try_from(s.to_string()).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn hands_are_dealt_from_the_seed() {
+        let mut game = Hearts::new(GameId::new(), HeartsOptions::default());
+        let mut other = Hearts::new(GameId::new(), HeartsOptions::default());
+        other.seed = game.seed;
+        other.deal();
+        assert_eq!(game.hands, other.hands);
+
+        game.hand_number += 1;
+        game.deal();
+        assert_ne!(game.hands, other.hands);
+    }
+
+    #[test]
+    fn passing_exchanges_cards_with_the_player_to_the_left() {
+        let mut game = Hearts::new(GameId::new(), HeartsOptions::default());
+        let passes: Vec<Vec<Card>> = game.hands.iter().map(|x| x[..3].to_vec()).collect();
+
+        assert!(game.pass(0, vec![passes[0][0]; 3]).is_err());
+        assert!(game.pass(0, passes[1].clone()).is_err());
+        for (player, cards) in passes.iter().enumerate() {
+            game.pass(player, cards.clone()).unwrap();
+        }
+
+        assert_eq!(game.phase, Phase::Playing);
+        for (player, cards) in passes.iter().enumerate() {
+            let receiver = &game.hands[(player + 1) % NUM_PLAYERS];
+            assert!(cards.iter().all(|x| receiver.contains(x)));
+            assert_eq!(receiver.len(), CARDS_PER_HAND);
+        }
+        assert!(game.hands[game.turn].contains(&TWO_OF_CLUBS));
+    }
+
+    #[test]
+    fn players_must_follow_suit_and_avoid_points_on_the_first_trick() {
+        let mut game = create_game([
+            "2C 3C 4H 5H 6H 7H 8H 9H TH JH QH KH AH",
+            "4C 2D 3D 4D 5D 6D 7D 8D 9D TD JD 2H 3S",
+            "5C 6C 7C 8C 9C TC JC QC KC AC 3H 2S 4S",
+            "QS 5S 6S 7S 8S 9S TS JS KS AS QD KD AD",
+        ]);
+
+        assert_eq!(game.legal_cards(0), vec![TWO_OF_CLUBS]);
+        assert!(game.play(0, card("3C")).is_err());
+        game.play(0, TWO_OF_CLUBS).unwrap();
+        assert_eq!(game.legal_cards(1), vec![card("4C")]);
+        game.play(1, card("4C")).unwrap();
+        game.play(2, card("AC")).unwrap();
+        // Without clubs, the queen of spades cannot be dropped on the first trick
+        assert!(game.play(3, QUEEN_OF_SPADES).is_err());
+        assert!(!game.legal_cards(3).contains(&QUEEN_OF_SPADES));
+        game.play(3, card("AD")).unwrap();
+
+        assert_eq!(game.turn, 2);
+        // Hearts have not been broken, so they cannot be led while other suits are left
+        assert!(game.play(2, card("3H")).is_err());
+        game.play(2, card("2S")).unwrap();
+        game.play(3, card("AS")).unwrap();
+        game.play(0, card("4H")).unwrap();
+        game.play(1, card("3S")).unwrap();
+
+        assert!(game.hearts_broken);
+        assert_eq!(game.hand_points, vec![0, 0, 0, 1]);
+        assert_eq!(game.turn, 3);
+    }
+
+    #[test]
+    fn points_go_to_the_trick_winner_unless_the_moon_is_shot() {
+        let mut game = create_game(["2C", "QS", "3C", "AH"]);
+        game.tricks_played = CARDS_PER_HAND - 1;
+        game.hearts_broken = true;
+
+        game.play(0, TWO_OF_CLUBS).unwrap();
+        game.play(1, QUEEN_OF_SPADES).unwrap();
+        game.play(2, card("3C")).unwrap();
+        game.play(3, card("AH")).unwrap();
+        assert_eq!(game.scores, vec![0, 0, 14, 0]);
+        assert_eq!(game.hand_number, 1);
+        assert_eq!(game.hands.iter().map(Vec::len).sum::<usize>(), 52);
+
+        game.hand_points = vec![0, TOTAL_POINTS, 0, 0];
+        game.score_hand();
+        assert_eq!(game.scores, vec![26, 0, 40, 26]);
+    }
+
+    #[test]
+    fn viewers_only_see_their_own_hand() {
+        let mut adapter = HeartsAdapter::new(GameId::new(), Value::Null).unwrap();
+        for player in ["a", "b", "c", "d"] {
+            adapter.add_player(player.to_string()).unwrap();
+        }
+
+        let spectator = adapter.get_viewer_state(None).unwrap().payload;
+        assert_eq!(spectator["hand"], Value::Null);
+        assert_eq!(spectator["hand_sizes"]["c"], 13);
+
+        let state = adapter.get_viewer_state(Some("c")).unwrap();
+        let hand: Vec<Card> = serde_json::from_value(state.payload["hand"].clone()).unwrap();
+        assert_eq!(hand, adapter.game.hands[2]);
+        assert_eq!(state.can_move.len(), NUM_PLAYERS);
+        let hidden_card = format!("\"{}\"", adapter.game.hands[1][0]);
+        assert!(!state.payload.to_string().contains(&hidden_card));
+    }
+}
@@ -0,0 +1,320 @@
+use crate::game::adapter::{GameAdapter, GenericGameMove};
+use crate::game::GameType;
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// Replaying a past state never takes more than this many moves and timeouts
+const SNAPSHOT_INTERVAL: usize = 50;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MoveRecord {
+    pub player: String,
+    pub payload: Value,
+    pub timestamp: DateTime<Utc>,
+    pub clock: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    clock: usize,
+    state: Value,
+}
+
+/// A change to a game, with the clock of its notifier after the change.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum HistoryEntry {
+    Move(MoveRecord),
+    Timeout { clock: usize },
+    Snapshot(Snapshot),
+}
+
+impl HistoryEntry {
+    fn clock(&self) -> usize {
+        match self {
+            HistoryEntry::Move(record) => record.clock,
+            HistoryEntry::Timeout { clock } => *clock,
+            HistoryEntry::Snapshot(snapshot) => snapshot.clock,
+        }
+    }
+}
+
+/// The accepted moves and timeouts of a game in the order they happened, with a snapshot of the
+/// adapter state after every update that cannot be replayed and after every few that can. Entries
+/// are only ever appended, so that they can be persisted as they are recorded.
+#[derive(Clone, Default, Deserialize)]
+#[serde(from = "LegacyHistory")]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+    // The number of entries recorded since the last snapshot
+    unsnapshotted: usize,
+    // The number of entries that have been persisted
+    persisted: usize,
+}
+
+/// Histories saved before they were persisted separately, which have a snapshot at every clock.
+#[derive(Deserialize)]
+struct LegacyHistory {
+    moves: Vec<MoveRecord>,
+    snapshots: Vec<Snapshot>,
+}
+
+impl From<LegacyHistory> for History {
+    fn from(legacy: LegacyHistory) -> Self {
+        // A move comes before the snapshot taken after it at the same clock
+        let entries = legacy
+            .moves
+            .into_iter()
+            .map(HistoryEntry::Move)
+            .merge_by(
+                legacy.snapshots.into_iter().map(HistoryEntry::Snapshot),
+                |a, b| a.clock() <= b.clock(),
+            )
+            .collect();
+
+        History {
+            entries,
+            unsnapshotted: 0,
+            persisted: 0,
+        }
+    }
+}
+
+impl History {
+    /// Continues a history whose entries have already been persisted.
+    pub fn from_entries(entries: Vec<HistoryEntry>) -> Self {
+        let unsnapshotted = entries
+            .iter()
+            .rev()
+            .take_while(|x| !matches!(x, HistoryEntry::Snapshot(_)))
+            .count();
+
+        History {
+            persisted: entries.len(),
+            entries,
+            unsnapshotted,
+        }
+    }
+
+    pub fn record_move(&mut self, record: MoveRecord) {
+        self.record(HistoryEntry::Move(record));
+    }
+
+    /// Records a move that did not change the clock, replacing any earlier one by the same player
+    /// that has not been persisted or snapshotted, since only the latest of them takes effect.
+    pub fn record_pending_move(&mut self, record: MoveRecord) {
+        let earlier = self.entries[self.persisted..]
+            .iter_mut()
+            .rev()
+            .take_while(|x| !matches!(x, HistoryEntry::Snapshot(_)) && x.clock() == record.clock)
+            .find(|x| matches!(x, HistoryEntry::Move(earlier) if earlier.player == record.player));
+
+        match earlier {
+            Some(entry) => *entry = HistoryEntry::Move(record),
+            None => self.record_move(record),
+        }
+    }
+
+    pub fn record_timeout(&mut self, clock: usize) {
+        self.record(HistoryEntry::Timeout { clock });
+    }
+
+    pub fn record_snapshot(&mut self, clock: usize, state: Value) {
+        self.record(HistoryEntry::Snapshot(Snapshot { clock, state }));
+        self.unsnapshotted = 0;
+    }
+
+    fn record(&mut self, entry: HistoryEntry) {
+        self.entries.push(entry);
+        self.unsnapshotted += 1;
+    }
+
+    /// Whether enough moves and timeouts have been recorded since the last snapshot to take another.
+    pub fn needs_snapshot(&self) -> bool {
+        self.unsnapshotted >= SNAPSHOT_INTERVAL
+    }
+
+    /// Returns the entries recorded since the history was last marked as persisted.
+    pub fn get_unpersisted(&self) -> &[HistoryEntry] {
+        &self.entries[self.persisted..]
+    }
+
+    pub fn mark_persisted(&mut self) {
+        self.persisted = self.entries.len();
+    }
+
+    pub fn get_moves(&self) -> Vec<MoveRecord> {
+        self.entries
+            .iter()
+            .filter_map(|x| match x {
+                HistoryEntry::Move(record) => Some(record.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the latest snapshot taken at or before `clock`, along with its clock and the
+    /// entries recorded after it.
+    fn snapshot_at(&self, clock: usize) -> Option<(usize, &Value, &[HistoryEntry])> {
+        let (index, snapshot) =
+            self.entries
+                .iter()
+                .enumerate()
+                .rev()
+                .find_map(|(index, entry)| match entry {
+                    HistoryEntry::Snapshot(snapshot) if snapshot.clock <= clock => {
+                        Some((index, snapshot))
+                    }
+                    _ => None,
+                })?;
+        Some((snapshot.clock, &snapshot.state, &self.entries[index + 1..]))
+    }
+
+    /// Rebuilds the adapter as it was at `clock` by replaying the moves and timeouts since the
+    /// latest snapshot before it, and returns it along with the clock at which it last changed.
+    pub fn state_at(
+        &self,
+        game_type: GameType,
+        clock: usize,
+    ) -> Option<actix_web::Result<(usize, Box<dyn GameAdapter>)>> {
+        let (snapshot_clock, state, entries) = self.snapshot_at(clock)?;
+
+        Some(
+            game_type
+                .info()
+                .load_adapter(state.clone())
+                .and_then(|mut adapter| {
+                    let mut state_clock = snapshot_clock;
+                    for entry in entries.iter().take_while(|x| x.clock() <= clock) {
+                        match entry {
+                            HistoryEntry::Move(record) => adapter.play_move(GenericGameMove {
+                                player: record.player.clone(),
+                                payload: record.payload.clone(),
+                            })?,
+                            HistoryEntry::Timeout { .. } => adapter.handle_timeout()?,
+                            HistoryEntry::Snapshot(_) => {}
+                        }
+                        state_clock = entry.clock();
+                    }
+                    Ok((state_clock, adapter))
+                }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn state_clock(history: &History, clock: usize) -> Option<usize> {
+        history.snapshot_at(clock).map(|(clock, _, _)| clock)
+    }
+
+    #[test]
+    fn snapshot_at_finds_latest_earlier_snapshot() {
+        let mut history = History::default();
+        history.record_snapshot(1, json!(1));
+        history.record_snapshot(2, json!(2));
+        history.record_snapshot(5, json!(5));
+
+        assert_eq!(state_clock(&history, 0), None);
+        assert_eq!(state_clock(&history, 1), Some(1));
+        assert_eq!(state_clock(&history, 4), Some(2));
+        assert_eq!(state_clock(&history, 9), Some(5));
+    }
+
+    #[test]
+    fn snapshot_at_same_clock_is_replaced() {
+        let mut history = History::default();
+        history.record_snapshot(1, json!(1));
+        history.record_snapshot(1, json!("replaced"));
+
+        assert_eq!(
+            history.snapshot_at(1).map(|(_, state, _)| state),
+            Some(&json!("replaced"))
+        );
+    }
+
+    #[test]
+    fn state_at_replays_moves_since_the_snapshot() {
+        let game_type = GameType::find("connect_4").unwrap();
+        let mut adapter = game_type
+            .info()
+            .new_adapter(crate::game::GameId::new(), Value::Null)
+            .unwrap();
+        let mut history = History::default();
+        for player in ["a", "b"] {
+            adapter.add_player(String::from(player)).unwrap();
+        }
+        history.record_snapshot(
+            adapter.get_notifier().clock(),
+            adapter.save_state().unwrap(),
+        );
+
+        let mut states = vec![];
+        for (player, column) in [("a", 0), ("b", 1), ("a", 0)] {
+            let payload = json!({ "game_type": "connect_4", "column": column });
+            adapter
+                .play_move(GenericGameMove {
+                    player: String::from(player),
+                    payload: payload.clone(),
+                })
+                .unwrap();
+            let clock = adapter.get_notifier().clock();
+            history.record_move(MoveRecord {
+                player: String::from(player),
+                payload,
+                timestamp: chrono::offset::Utc::now(),
+                clock,
+            });
+            states.push((clock, adapter.get_encoded_state().unwrap().payload));
+        }
+
+        for (clock, payload) in states {
+            let (state_clock, replayed) = history.state_at(game_type, clock).unwrap().unwrap();
+            assert_eq!(state_clock, clock);
+            assert_eq!(replayed.get_encoded_state().unwrap().payload, payload);
+        }
+        assert_eq!(history.get_moves().len(), 3);
+    }
+
+    #[test]
+    fn snapshots_are_needed_after_enough_entries() {
+        let mut history = History::default();
+        history.record_snapshot(1, json!(1));
+        for clock in 2..SNAPSHOT_INTERVAL + 2 {
+            assert!(!history.needs_snapshot());
+            history.record_timeout(clock);
+        }
+        assert!(history.needs_snapshot());
+
+        assert_eq!(history.get_unpersisted().len(), SNAPSHOT_INTERVAL + 1);
+        history.mark_persisted();
+        assert!(history.get_unpersisted().is_empty());
+        assert!(History::from_entries(history.entries).needs_snapshot());
+    }
+
+    #[test]
+    fn legacy_histories_are_read_in_order() {
+        let history: History = serde_json::from_value(json!({
+            "moves": [
+                { "player": "a", "payload": 1, "timestamp": "2021-01-01T00:00:00Z", "clock": 2 },
+            ],
+            "snapshots": [{ "clock": 1, "state": 1 }, { "clock": 2, "state": 2 }],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            history
+                .entries
+                .iter()
+                .map(|x| x.clock())
+                .collect::<Vec<_>>(),
+            vec![1, 2, 2]
+        );
+        assert!(matches!(history.entries[2], HistoryEntry::Snapshot(_)));
+    }
+}
@@ -0,0 +1,479 @@
+use crate::game::adapter::{
+    GameAdapter, GameAdapterError, GameAdapterErrorType, GenericGameMove, GenericGameState, Stage,
+};
+use crate::game::GameId;
+use crate::notify::Notifier;
+use actix_web::error::{InternalError, JsonPayloadError};
+use actix_web::HttpResponse;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::vec;
+use std::vec::Vec;
+
+pub const NUM_PLAYERS: usize = 2;
+const DEFAULT_ROUNDS: usize = 10;
+const MAX_ROUNDS: usize = 10000;
+
+#[derive(Serialize, Deserialize)]
+pub struct IteratedAdapter {
+    game_id: GameId,
+    players: Vec<String>,
+    stage: Stage,
+    #[serde(skip, default = "Notifier::new")]
+    notifier: Notifier,
+    game: Iterated,
+    winner: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct IteratedRequestPayload {
+    action: Action,
+}
+
+#[derive(Serialize)]
+struct RoundPayload<'a> {
+    actions: HashMap<&'a String, Action>,
+    payoffs: HashMap<&'a String, i64>,
+}
+
+#[derive(Serialize)]
+struct IteratedResponsePayload<'a> {
+    actions: &'static [Action],
+    rounds: Vec<RoundPayload<'a>>,
+    scores: HashMap<&'a String, i64>,
+    options: &'a IteratedOptions,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum IteratedGame {
+    RockPaperScissors,
+    PrisonersDilemma,
+}
+
+impl IteratedGame {
+    /// The actions a player can choose from, in the order of the rows and columns of the payoffs.
+    fn actions(self) -> &'static [Action] {
+        match self {
+            IteratedGame::RockPaperScissors => &[Action::Rock, Action::Paper, Action::Scissors],
+            IteratedGame::PrisonersDilemma => &[Action::Cooperate, Action::Defect],
+        }
+    }
+
+    fn default_payoffs(self) -> Vec<Vec<i64>> {
+        match self {
+            IteratedGame::RockPaperScissors => vec![vec![0, -1, 1], vec![1, 0, -1], vec![-1, 1, 0]],
+            IteratedGame::PrisonersDilemma => vec![vec![3, 0], vec![5, 1]],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum Action {
+    Rock,
+    Paper,
+    Scissors,
+    Cooperate,
+    Defect,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+struct IteratedOptions {
+    game: IteratedGame,
+    rounds: usize,
+    // The payoff to a player choosing the row's action against an opponent choosing the column's,
+    // or empty for the game's usual payoffs
+    payoffs: Vec<Vec<i64>>,
+}
+
+impl Default for IteratedOptions {
+    fn default() -> Self {
+        IteratedOptions {
+            game: IteratedGame::RockPaperScissors,
+            rounds: DEFAULT_ROUNDS,
+            payoffs: vec![],
+        }
+    }
+}
+
+impl IteratedOptions {
+    fn validate(&self) -> Result<(), String> {
+        let actions = self.game.actions().len();
+
+        if !(1..=MAX_ROUNDS).contains(&self.rounds) {
+            Err(format!("rounds must be between 1 and {}", MAX_ROUNDS))
+        } else if !self.payoffs.is_empty()
+            && (self.payoffs.len() != actions || self.payoffs.iter().any(|x| x.len() != actions))
+        {
+            Err(format!(
+                "payoffs must be a {} by {} matrix for this game",
+                actions, actions
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Round {
+    actions: Vec<Action>, // indexed by player
+    payoffs: Vec<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Iterated {
+    game_id: GameId,
+    // Actions chosen in the current round, which are revealed once every player has chosen
+    moves: HashMap<usize, Action>,
+    rounds: Vec<Round>,
+    scores: Vec<i64>,
+    options: IteratedOptions,
+}
+
+impl GameAdapter for IteratedAdapter {
+    fn new(game_id: GameId, options: Value) -> actix_web::Result<Self>
+    where
+        Self: Sized,
+    {
+        let options = serde_json::from_value::<Option<IteratedOptions>>(options)
+            .map_err(|e| e.to_string())
+            .and_then(|options| {
+                let mut options = options.unwrap_or_default();
+                options.validate()?;
+                // Echo the payoffs that are used, even if they were left out
+                if options.payoffs.is_empty() {
+                    options.payoffs = options.game.default_payoffs();
+                }
+                Ok(options)
+            })
+            .map_err(|e| {
+                GameAdapterError::actix_err(game_id, GameAdapterErrorType::InvalidOptions(e))
+            })?;
+
+        Ok(IteratedAdapter {
+            game_id,
+            players: vec![],
+            stage: Stage::Waiting,
+            notifier: Notifier::new(),
+            game: Iterated::new(game_id, options),
+            winner: vec![],
+        })
+    }
+
+    fn load_state(state: Value) -> actix_web::Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(serde_json::from_value(state)?)
+    }
+
+    fn save_state(&self) -> actix_web::Result<Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn get_notifier(&self) -> &Notifier {
+        &self.notifier
+    }
+
+    fn add_player(&mut self, username: String) -> actix_web::Result<()> {
+        assert!(self.players.len() < NUM_PLAYERS);
+        assert_eq!(self.stage, Stage::Waiting);
+
+        self.players.push(username);
+        if self.players.len() == NUM_PLAYERS {
+            self.stage = Stage::InProgress;
+        }
+        self.notifier.send();
+        Ok(())
+    }
+
+    fn has_player(&self, username: &str) -> bool {
+        self.players.iter().any(|s| s.eq(username))
+    }
+
+    fn play_move(&mut self, game_move: GenericGameMove) -> actix_web::Result<()> {
+        if self.stage != Stage::InProgress {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidGameStage(self.stage),
+            ));
+        }
+
+        let request_payload = serde_json::from_value::<IteratedRequestPayload>(game_move.payload)
+            .map_err(|e| {
+            InternalError::from_response(
+                "",
+                HttpResponse::BadRequest()
+                    .content_type("text/plain")
+                    .body(JsonPayloadError::Deserialize(e).to_string()),
+            )
+        })?;
+
+        let player = match self.players.iter().position(|x| *x == game_move.player) {
+            Some(player) if !self.game.moves.contains_key(&player) => player,
+            _ => {
+                return Err(GameAdapterError::actix_err(
+                    self.game_id,
+                    GameAdapterErrorType::InvalidPlayer(game_move.player),
+                ))
+            }
+        };
+
+        self.game.record_move(player, request_payload.action)?;
+
+        self.check_game_over();
+        self.notifier.send();
+        Ok(())
+    }
+
+    fn handle_timeout(&mut self) -> actix_web::Result<()> {
+        if self.stage != Stage::InProgress {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidGameStage(self.stage),
+            ));
+        }
+
+        self.game.repeat_missing_moves()?;
+
+        self.check_game_over();
+        self.notifier.send();
+        Ok(())
+    }
+
+    fn end_game(&mut self, winners: Vec<String>) {
+        self.winner = winners;
+        self.stage = Stage::Ended;
+    }
+
+    fn get_stage(&self) -> Stage {
+        self.stage
+    }
+
+    fn get_encoded_state(&self) -> actix_web::Result<GenericGameState> {
+        let by_player = |values: &[i64]| -> HashMap<&String, i64> {
+            self.players.iter().zip(values.iter().copied()).collect()
+        };
+        let rounds = self
+            .game
+            .rounds
+            .iter()
+            .map(|round| RoundPayload {
+                actions: self
+                    .players
+                    .iter()
+                    .zip(round.actions.iter().copied())
+                    .collect(),
+                payoffs: by_player(&round.payoffs),
+            })
+            .collect();
+
+        Ok(GenericGameState {
+            players: self.players.clone(),
+            stage: self.stage,
+            can_move: if self.stage == Stage::InProgress {
+                (0..NUM_PLAYERS)
+                    .filter(|x| !self.game.moves.contains_key(x))
+                    .map(|x| self.players[x].clone())
+                    .collect()
+            } else {
+                vec![]
+            },
+            winners: self.winner.clone(),
+            deadline: None,
+            termination: None,
+            draw_offers: vec![],
+            payload: serde_json::to_value(IteratedResponsePayload {
+                actions: self.game.options.game.actions(),
+                rounds,
+                scores: by_player(&self.game.scores),
+                options: &self.game.options,
+            })?,
+        })
+    }
+
+    fn has_private_moves(&self) -> bool {
+        true
+    }
+}
+
+impl IteratedAdapter {
+    /// Ends the game after the last round, with the highest score winning.
+    fn check_game_over(&mut self) {
+        if self.game.rounds.len() < self.game.options.rounds {
+            return;
+        }
+
+        let [first, second] = [self.game.scores[0], self.game.scores[1]];
+        if first != second {
+            let winner = if first > second { 0 } else { 1 };
+            self.winner.push(self.players[winner].clone());
+        }
+        self.stage = Stage::Ended;
+    }
+}
+
+impl Iterated {
+    fn new(game_id: GameId, options: IteratedOptions) -> Self {
+        Iterated {
+            game_id,
+            moves: HashMap::new(),
+            rounds: vec![],
+            scores: vec![0; NUM_PLAYERS],
+            options,
+        }
+    }
+
+    fn action_index(&self, action: Action) -> Option<usize> {
+        self.options
+            .game
+            .actions()
+            .iter()
+            .position(|&x| x == action)
+    }
+
+    /// Reveals the actions chosen by both players and adds their payoffs to the scores.
+    fn resolve_round(&mut self) {
+        let actions: Vec<Action> = (0..NUM_PLAYERS).map(|x| self.moves[&x]).collect();
+        let indices: Vec<usize> = actions
+            .iter()
+            .map(|&x| self.action_index(x).expect("actions have been validated"))
+            .collect();
+        let payoffs: Vec<i64> = (0..NUM_PLAYERS)
+            .map(|x| self.options.payoffs[indices[x]][indices[1 - x]])
+            .collect();
+
+        for (score, payoff) in self.scores.iter_mut().zip(payoffs.iter()) {
+            *score += payoff;
+        }
+        self.rounds.push(Round { actions, payoffs });
+        self.moves.clear();
+    }
+
+    /// Makes every player that has not chosen an action repeat their last one, or choose the first
+    /// action in the first round.
+    fn repeat_missing_moves(&mut self) -> actix_web::Result<()> {
+        for player in 0..NUM_PLAYERS {
+            if !self.moves.contains_key(&player) {
+                let action = self
+                    .rounds
+                    .last()
+                    .map_or(self.options.game.actions()[0], |x| x.actions[player]);
+                self.moves.insert(player, action);
+            }
+        }
+
+        self.resolve_round();
+        Ok(())
+    }
+
+    fn record_move(&mut self, player: usize, action: Action) -> actix_web::Result<()> {
+        if self.action_index(action).is_none() {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidMove(format!(
+                    "{} is not an action in this game",
+                    serde_json::to_string(&action)?
+                )),
+            ));
+        }
+        assert_eq!(self.moves.insert(player, action), None);
+
+        if self.moves.len() == NUM_PLAYERS {
+            self.resolve_round();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_game(options: Value) -> IteratedAdapter {
+        let mut adapter = IteratedAdapter::new(GameId::new(), options).unwrap();
+        adapter.add_player("a".to_string()).unwrap();
+        adapter.add_player("b".to_string()).unwrap();
+        adapter
+    }
+
+    fn play(adapter: &mut IteratedAdapter, player: &str, action: &str) -> actix_web::Result<()> {
+        adapter.play_move(GenericGameMove {
+            player: player.to_string(),
+            payload: serde_json::json!({ "game_type": "iterated", "action": action }),
+        })
+    }
+
+    #[test]
+    fn rounds_resolve_once_both_players_have_moved() {
+        let mut adapter = create_game(Value::Null);
+
+        play(&mut adapter, "a", "rock").unwrap();
+        assert!(play(&mut adapter, "a", "paper").is_err());
+        let state = adapter.get_encoded_state().unwrap();
+        assert_eq!(state.can_move, vec!["b".to_string()]);
+        assert_eq!(state.payload["rounds"], serde_json::json!([]));
+
+        play(&mut adapter, "b", "scissors").unwrap();
+        assert_eq!(adapter.game.scores, vec![1, -1]);
+        assert_eq!(
+            adapter.game.rounds[0].actions,
+            vec![Action::Rock, Action::Scissors]
+        );
+        assert_eq!(adapter.get_encoded_state().unwrap().can_move.len(), 2);
+    }
+
+    #[test]
+    fn prisoners_dilemma_uses_custom_payoffs_and_ends_after_the_last_round() {
+        let options = serde_json::json!({
+            "game": "prisoners_dilemma",
+            "rounds": 2,
+            "payoffs": [[2, -1], [3, 0]],
+        });
+        let mut adapter = create_game(options);
+
+        assert!(play(&mut adapter, "a", "rock").is_err());
+        play(&mut adapter, "a", "cooperate").unwrap();
+        play(&mut adapter, "b", "defect").unwrap();
+        play(&mut adapter, "a", "cooperate").unwrap();
+        play(&mut adapter, "b", "cooperate").unwrap();
+
+        assert_eq!(adapter.game.scores, vec![1, 5]);
+        assert_eq!(adapter.stage, Stage::Ended);
+        assert_eq!(adapter.winner, vec!["b".to_string()]);
+    }
+
+    #[test]
+    fn missing_moves_repeat_the_last_action() {
+        let mut adapter = create_game(Value::Null);
+
+        adapter.handle_timeout().unwrap();
+        play(&mut adapter, "a", "paper").unwrap();
+        adapter.handle_timeout().unwrap();
+
+        let actions: Vec<_> = adapter.game.rounds.iter().map(|x| &x.actions).collect();
+        assert_eq!(
+            actions,
+            [
+                &vec![Action::Rock, Action::Rock],
+                &vec![Action::Paper, Action::Rock]
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_options_are_rejected() {
+        for options in [
+            serde_json::json!({ "rounds": 0 }),
+            serde_json::json!({ "game": "prisoners_dilemma", "payoffs": [[1, 2, 3], [4, 5, 6]] }),
+            serde_json::json!({ "game": "chicken" }),
+        ] {
+            assert!(IteratedAdapter::new(GameId::new(), options).is_err());
+        }
+    }
+}
@@ -0,0 +1,47 @@
+// Along a row, along a column and along both diagonals
+const DIRECTIONS: [(isize, isize); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+
+/// Whether the cell at `(row, column)` is part of a line of at least `length` cells that all
+/// belong to the same player, as told by `belongs`, which is given a row and a column that may be
+/// off the board.
+pub fn in_a_row(
+    row: usize,
+    column: usize,
+    length: usize,
+    belongs: impl Fn(isize, isize) -> bool,
+) -> bool {
+    DIRECTIONS.iter().any(|&(row_step, column_step)| {
+        let count_towards = |sign: isize| {
+            (1..)
+                .take_while(|&distance| {
+                    belongs(
+                        row as isize + sign * distance * row_step,
+                        column as isize + sign * distance * column_step,
+                    )
+                })
+                .count()
+        };
+
+        count_towards(1) + count_towards(-1) + 1 >= length
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_counted_on_both_sides_of_the_cell() {
+        let board = ["x.x", ".x.", "xx."];
+        let belongs = |row: isize, column: isize| {
+            (0..3).contains(&row)
+                && (0..3).contains(&column)
+                && board[row as usize].as_bytes()[column as usize] == b'x'
+        };
+
+        assert!(in_a_row(1, 1, 3, belongs));
+        assert!(in_a_row(2, 1, 2, belongs));
+        assert!(!in_a_row(2, 1, 3, belongs));
+        assert!(!in_a_row(0, 2, 4, belongs));
+    }
+}
@@ -0,0 +1,319 @@
+use crate::game::{validate_username, GameId, GameManager, GameSettings, GameType, SessionId};
+use actix_web::http::StatusCode;
+use actix_web::{ResponseError, Result};
+use derive_more::Display;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::timeout;
+
+const MATCHMAKING_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Display)]
+pub enum MatchmakingError {
+    #[display(fmt = "player {} is already queued", _0)]
+    AlreadyQueued(String),
+    #[display(fmt = "no match found within {} seconds", MATCHMAKING_TIMEOUT_SECS)]
+    Timeout,
+    #[display(fmt = "failed to create a game for the match")]
+    GameCreationFailed,
+    #[display(fmt = "{} games are for {} to {} players", _0, _1, _2)]
+    InvalidPlayerCount(GameType, usize, usize),
+}
+
+impl ResponseError for MatchmakingError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            MatchmakingError::AlreadyQueued(_) => StatusCode::CONFLICT,
+            MatchmakingError::Timeout => StatusCode::REQUEST_TIMEOUT,
+            MatchmakingError::GameCreationFailed => StatusCode::INTERNAL_SERVER_ERROR,
+            MatchmakingError::InvalidPlayerCount(..) => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct MatchedSeat {
+    pub game_id: GameId,
+    pub session_id: SessionId,
+}
+
+struct QueuedPlayer {
+    ticket: usize,
+    username: String,
+    sender: oneshot::Sender<Option<MatchedSeat>>,
+}
+
+// Players queue for a type of game with a number of players
+type QueueKey = (GameType, usize);
+
+/// Removes a player from the queue if their request ends before they are matched.
+struct QueueTicket<'a> {
+    queue: &'a MatchmakingQueue,
+    key: QueueKey,
+    ticket: usize,
+}
+
+impl QueueTicket<'_> {
+    /// Removes the player from the queue, returning false if they have already been matched.
+    fn leave(&self) -> bool {
+        match self.queue.queues.lock().unwrap().get_mut(&self.key) {
+            Some(players) => {
+                let queued = players.len();
+                players.retain(|x| x.ticket != self.ticket);
+                players.len() < queued
+            }
+            None => false,
+        }
+    }
+}
+
+impl Drop for QueueTicket<'_> {
+    fn drop(&mut self) {
+        self.leave();
+    }
+}
+
+pub struct MatchmakingQueue {
+    queues: Mutex<HashMap<QueueKey, Vec<QueuedPlayer>>>,
+    next_ticket: AtomicUsize,
+    timeout: Duration,
+}
+
+impl MatchmakingQueue {
+    pub fn new() -> Self {
+        MatchmakingQueue {
+            queues: Mutex::new(HashMap::new()),
+            next_ticket: AtomicUsize::new(0),
+            timeout: Duration::from_secs(MATCHMAKING_TIMEOUT_SECS),
+        }
+    }
+
+    /// Queues a player for a type of game with the given number of players, or the default for the
+    /// game, and waits until enough players are queued to fill a game, which is then created with
+    /// every matched player already seated.
+    pub async fn enqueue(
+        &self,
+        game_manager: &GameManager,
+        game_type: GameType,
+        num_players: Option<usize>,
+        username: String,
+    ) -> Result<MatchedSeat> {
+        validate_username(&username)?;
+
+        let info = game_type.info();
+        let num_players = num_players.unwrap_or(info.num_players);
+        if !info.supports_players(num_players) {
+            return Err(actix_web::Error::from(
+                MatchmakingError::InvalidPlayerCount(game_type, info.min_players, info.max_players),
+            ));
+        }
+
+        let (sender, mut receiver) = oneshot::channel();
+        let ticket = QueueTicket {
+            queue: self,
+            key: (game_type, num_players),
+            ticket: self.next_ticket.fetch_add(1, Ordering::SeqCst),
+        };
+
+        let matched = {
+            let mut queues = self.queues.lock().unwrap();
+            let players = queues.entry(ticket.key).or_default();
+
+            if players.iter().any(|x| x.username == username) {
+                return Err(actix_web::Error::from(MatchmakingError::AlreadyQueued(
+                    username,
+                )));
+            }
+
+            players.push(QueuedPlayer {
+                ticket: ticket.ticket,
+                username,
+                sender,
+            });
+
+            if players.len() >= num_players {
+                players.drain(..num_players).collect()
+            } else {
+                vec![]
+            }
+        };
+
+        if !matched.is_empty() {
+            MatchmakingQueue::start_game(game_manager, game_type, matched);
+        }
+
+        let result = match timeout(self.timeout, &mut receiver).await {
+            Ok(result) => result,
+            // A player matched just as the timeout fired has left the queue, and their seat is on
+            // its way
+            Err(_) if !ticket.leave() => receiver.await,
+            Err(_) => return Err(actix_web::Error::from(MatchmakingError::Timeout)),
+        };
+
+        match result {
+            Ok(Some(seat)) => Ok(seat),
+            _ => Err(actix_web::Error::from(MatchmakingError::GameCreationFailed)),
+        }
+    }
+
+    fn start_game(game_manager: &GameManager, game_type: GameType, players: Vec<QueuedPlayer>) {
+        let options = game_type.info().options_for_players(players.len());
+        let game_id = match game_manager.create_game(game_type, options, GameSettings::default()) {
+            Ok(game_id) => game_id,
+            Err(e) => {
+                log::error!("failed to create game for matchmaking: {}", e);
+                return;
+            }
+        };
+
+        for player in players {
+            let session_id = SessionId::new();
+            let seat = match game_manager.assign_seat(game_id, player.username, session_id) {
+                Ok(()) => Some(MatchedSeat {
+                    game_id,
+                    session_id,
+                }),
+                Err(e) => {
+                    log::error!("failed to seat matched player in {}: {}", game_id, e);
+                    None
+                }
+            };
+
+            let _ = player.sender.send(seat);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::adapter::Stage;
+    use crate::game::storage::MemoryStorage;
+    use actix_web::rt::System;
+    use std::sync::Arc;
+    use tokio::time::delay_for;
+
+    fn create_queue() -> (GameManager, MatchmakingQueue) {
+        let queue = MatchmakingQueue {
+            timeout: Duration::from_millis(50),
+            ..MatchmakingQueue::new()
+        };
+        (GameManager::new(Arc::new(MemoryStorage)).unwrap(), queue)
+    }
+
+    fn is_error(result: &Result<MatchedSeat>, expected: fn(&MatchmakingError) -> bool) -> bool {
+        match result {
+            Err(e) => e.as_error::<MatchmakingError>().is_some_and(expected),
+            Ok(_) => false,
+        }
+    }
+
+    #[test]
+    fn queued_players_are_seated_in_the_same_game() {
+        System::new("matchmaking").block_on(async {
+            let (manager, queue) = create_queue();
+            let connect_4 = GameType::find("connect_4").unwrap();
+
+            let (a, b) = futures::join!(
+                queue.enqueue(&manager, connect_4, None, String::from("a")),
+                queue.enqueue(&manager, connect_4, None, String::from("b")),
+            );
+            let (a, b) = (a.unwrap(), b.unwrap());
+
+            assert_eq!(a.game_id, b.game_id);
+            assert_eq!(
+                manager
+                    .get_state(a.game_id, Some(a.session_id))
+                    .unwrap()
+                    .players
+                    .len(),
+                2
+            );
+            assert!(queue.queues.lock().unwrap()[&(connect_4, 2)].is_empty());
+        });
+    }
+
+    #[test]
+    fn players_are_matched_for_the_number_of_players_they_ask_for() {
+        System::new("matchmaking").block_on(async {
+            let (manager, queue) = create_queue();
+            let snake = GameType::find("snake").unwrap();
+
+            let (a, b, c) = futures::join!(
+                queue.enqueue(&manager, snake, Some(2), String::from("a")),
+                queue.enqueue(&manager, snake, None, String::from("b")),
+                queue.enqueue(&manager, snake, Some(2), String::from("c")),
+            );
+            let (a, c) = (a.unwrap(), c.unwrap());
+
+            assert_eq!(a.game_id, c.game_id);
+            let state = manager.get_state(a.game_id, None).unwrap();
+            assert_eq!(state.players, vec!["a", "c"]);
+            assert_eq!(state.stage, Stage::InProgress);
+            assert!(is_error(&b, |x| matches!(x, MatchmakingError::Timeout)));
+
+            let result = queue
+                .enqueue(&manager, snake, Some(9), String::from("d"))
+                .await;
+            assert!(is_error(&result, |x| matches!(
+                x,
+                MatchmakingError::InvalidPlayerCount(..)
+            )));
+        });
+    }
+
+    #[test]
+    fn unmatched_players_time_out_and_leave_the_queue() {
+        System::new("matchmaking").block_on(async {
+            let (manager, queue) = create_queue();
+            let connect_4 = GameType::find("connect_4").unwrap();
+
+            let (a, b) = futures::join!(
+                queue.enqueue(&manager, connect_4, None, String::from("a")),
+                queue.enqueue(&manager, connect_4, None, String::from("a")),
+            );
+
+            assert!(is_error(&a, |x| matches!(x, MatchmakingError::Timeout)));
+            assert!(is_error(&b, |x| matches!(
+                x,
+                MatchmakingError::AlreadyQueued(_)
+            )));
+            assert!(queue.queues.lock().unwrap()[&(connect_4, 2)].is_empty());
+        });
+    }
+
+    #[test]
+    fn players_matched_as_they_time_out_get_their_seat() {
+        System::new("matchmaking").block_on(async {
+            let (manager, queue) = create_queue();
+            let connect_4 = GameType::find("connect_4").unwrap();
+
+            // Take the player out of the queue as a match would, but only seat them after the
+            // timeout has fired
+            let seat_late = async {
+                delay_for(Duration::from_millis(10)).await;
+                let player = queue
+                    .queues
+                    .lock()
+                    .unwrap()
+                    .get_mut(&(connect_4, 2))
+                    .unwrap()
+                    .remove(0);
+                delay_for(queue.timeout).await;
+                MatchmakingQueue::start_game(&manager, connect_4, vec![player]);
+            };
+            let (seat, _) = futures::join!(
+                queue.enqueue(&manager, connect_4, None, String::from("a")),
+                seat_late
+            );
+
+            let seat = seat.unwrap();
+            assert!(manager
+                .get_state(seat.game_id, Some(seat.session_id))
+                .is_ok());
+        });
+    }
+}
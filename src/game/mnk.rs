@@ -0,0 +1,376 @@
+use crate::game::adapter::{
+    GameAdapter, GameAdapterError, GameAdapterErrorType, GenericGameMove, GenericGameState, Stage,
+};
+use crate::game::lines;
+use crate::game::GameId;
+use crate::notify::Notifier;
+use actix_web::error::{InternalError, JsonPayloadError};
+use actix_web::HttpResponse;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::vec;
+use std::vec::Vec;
+
+pub const NUM_PLAYERS: usize = 2;
+const DEFAULT_ROWS: usize = 3;
+const DEFAULT_COLUMNS: usize = 3;
+const DEFAULT_CONNECT: usize = 3;
+const MAX_BOARD_SIZE: usize = 19;
+const MIN_CONNECT: usize = 2;
+
+#[derive(Serialize, Deserialize)]
+pub struct MnkAdapter {
+    game_id: GameId,
+    players: Vec<String>,
+    stage: Stage,
+    #[serde(skip, default = "Notifier::new")]
+    notifier: Notifier,
+    game: Mnk,
+    winner: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct MnkRequestPayload {
+    row: usize,
+    column: usize,
+}
+
+#[derive(Serialize)]
+struct MnkResponsePayload<'a> {
+    cells: Vec<Vec<Option<&'a String>>>,
+    options: &'a MnkOptions,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+struct MnkOptions {
+    rows: usize,
+    columns: usize,
+    connect: usize,
+}
+
+impl Default for MnkOptions {
+    fn default() -> Self {
+        MnkOptions {
+            rows: DEFAULT_ROWS,
+            columns: DEFAULT_COLUMNS,
+            connect: DEFAULT_CONNECT,
+        }
+    }
+}
+
+impl MnkOptions {
+    fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_BOARD_SIZE).contains(&self.rows) {
+            Err(format!("rows must be between 1 and {}", MAX_BOARD_SIZE))
+        } else if !(1..=MAX_BOARD_SIZE).contains(&self.columns) {
+            Err(format!("columns must be between 1 and {}", MAX_BOARD_SIZE))
+        } else if self.connect < MIN_CONNECT || self.connect > self.rows.max(self.columns) {
+            Err(format!(
+                "connect must be between {} and the number of rows or columns",
+                MIN_CONNECT
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Mnk {
+    game_id: GameId,
+    // Index of the player to move next
+    turn: usize,
+    board: Vec<Vec<Option<usize>>>, // vector of rows, each holding the index of a player.
+    options: MnkOptions,
+}
+
+impl GameAdapter for MnkAdapter {
+    fn new(game_id: GameId, options: Value) -> actix_web::Result<Self>
+    where
+        Self: Sized,
+    {
+        let options = serde_json::from_value::<Option<MnkOptions>>(options)
+            .map_err(|e| e.to_string())
+            .and_then(|options| {
+                let options = options.unwrap_or_default();
+                options.validate()?;
+                Ok(options)
+            })
+            .map_err(|e| {
+                GameAdapterError::actix_err(game_id, GameAdapterErrorType::InvalidOptions(e))
+            })?;
+
+        Ok(MnkAdapter {
+            game_id,
+            players: vec![],
+            stage: Stage::Waiting,
+            notifier: Notifier::new(),
+            game: Mnk::new(game_id, options),
+            winner: vec![],
+        })
+    }
+
+    fn load_state(state: Value) -> actix_web::Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(serde_json::from_value(state)?)
+    }
+
+    fn save_state(&self) -> actix_web::Result<Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn get_notifier(&self) -> &Notifier {
+        &self.notifier
+    }
+
+    fn add_player(&mut self, username: String) -> actix_web::Result<()> {
+        assert!(self.players.len() < NUM_PLAYERS);
+        assert_eq!(self.stage, Stage::Waiting);
+
+        self.players.push(username);
+        if self.players.len() == NUM_PLAYERS {
+            self.stage = Stage::InProgress;
+        }
+        self.notifier.send();
+        Ok(())
+    }
+
+    fn has_player(&self, username: &str) -> bool {
+        self.players.iter().any(|s| s.eq(username))
+    }
+
+    fn play_move(&mut self, game_move: GenericGameMove) -> actix_web::Result<()> {
+        if self.stage != Stage::InProgress {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidGameStage(self.stage),
+            ));
+        }
+
+        let request_payload = serde_json::from_value::<MnkRequestPayload>(game_move.payload)
+            .map_err(|e| {
+                InternalError::from_response(
+                    "",
+                    HttpResponse::BadRequest()
+                        .content_type("text/plain")
+                        .body(JsonPayloadError::Deserialize(e).to_string()),
+                )
+            })?;
+
+        if self.players[self.game.turn] != game_move.player {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidPlayer(game_move.player),
+            ));
+        }
+
+        let (row, column) = (request_payload.row, request_payload.column);
+        self.game.place(row, column)?;
+
+        if self.game.winning_move(row, column) {
+            self.winner.push(game_move.player);
+            self.stage = Stage::Ended;
+        } else if self.game.is_full() {
+            self.stage = Stage::Ended;
+        } else {
+            self.game.turn = (self.game.turn + 1) % NUM_PLAYERS;
+        }
+        self.notifier.send();
+        Ok(())
+    }
+
+    fn handle_timeout(&mut self) -> actix_web::Result<()> {
+        if self.stage != Stage::InProgress {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidGameStage(self.stage),
+            ));
+        }
+
+        // The player who ran out of time forfeits
+        self.winner = vec![self.players[(self.game.turn + 1) % NUM_PLAYERS].clone()];
+        self.stage = Stage::Ended;
+        self.notifier.send();
+        Ok(())
+    }
+
+    fn end_game(&mut self, winners: Vec<String>) {
+        self.winner = winners;
+        self.stage = Stage::Ended;
+    }
+
+    fn get_stage(&self) -> Stage {
+        self.stage
+    }
+
+    fn get_encoded_state(&self) -> actix_web::Result<GenericGameState> {
+        let cells = self
+            .game
+            .board
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| cell.map(|player| &self.players[player]))
+                    .collect()
+            })
+            .collect();
+
+        Ok(GenericGameState {
+            players: self.players.clone(),
+            stage: self.stage,
+            can_move: if self.stage == Stage::InProgress {
+                vec![self.players[self.game.turn].clone()]
+            } else {
+                vec![]
+            },
+            winners: self.winner.clone(),
+            deadline: None,
+            termination: None,
+            draw_offers: vec![],
+            payload: serde_json::to_value(MnkResponsePayload {
+                cells,
+                options: &self.game.options,
+            })?,
+        })
+    }
+}
+
+impl Mnk {
+    fn new(game_id: GameId, options: MnkOptions) -> Self {
+        Mnk {
+            game_id,
+            turn: 0,
+            board: vec![vec![None; options.columns]; options.rows],
+            options,
+        }
+    }
+
+    fn get_cell_at(&self, row: isize, column: isize) -> Option<usize> {
+        if row < 0 || column < 0 {
+            return None;
+        }
+        *self.board.get(row as usize)?.get(column as usize)?
+    }
+
+    fn place(&mut self, row: usize, column: usize) -> actix_web::Result<()> {
+        let invalid_move = |reason: String| {
+            GameAdapterError::actix_err(self.game_id, GameAdapterErrorType::InvalidMove(reason))
+        };
+
+        match self.board.get(row).and_then(|x| x.get(column)) {
+            None => Err(invalid_move(format!(
+                "cell ({}, {}) does not exist",
+                row, column
+            ))),
+            Some(Some(_)) => Err(invalid_move(format!(
+                "cell ({}, {}) is already taken",
+                row, column
+            ))),
+            Some(None) => {
+                self.board[row][column] = Some(self.turn);
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether the piece at the given cell is part of a line of at least `connect` pieces.
+    fn winning_move(&self, row: usize, column: usize) -> bool {
+        let player = self.board[row][column];
+        lines::in_a_row(row, column, self.options.connect, |row, column| {
+            self.get_cell_at(row, column) == player
+        })
+    }
+
+    fn is_full(&self) -> bool {
+        self.board.iter().flatten().all(Option::is_some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play_moves(game: &mut Mnk, moves: &[(usize, usize)]) {
+        for &(row, column) in moves {
+            game.place(row, column).unwrap();
+            game.turn = (game.turn + 1) % NUM_PLAYERS;
+        }
+    }
+
+    #[test]
+    fn lines_in_every_direction_win() {
+        let lines = [
+            [(1, 0), (1, 1), (1, 2)],
+            [(0, 0), (1, 0), (2, 0)],
+            [(0, 0), (1, 1), (2, 2)],
+            [(0, 2), (1, 1), (2, 0)],
+        ];
+
+        for line in lines {
+            let mut game = Mnk::new(GameId::new(), MnkOptions::default());
+            play_moves(&mut game, &[line[0], (0, 1), line[1]]);
+            assert!(!game.winning_move(line[1].0, line[1].1));
+
+            game.board[line[2].0][line[2].1] = Some(0);
+            assert!(game.winning_move(line[2].0, line[2].1));
+            assert!(game.winning_move(line[0].0, line[0].1));
+        }
+    }
+
+    #[test]
+    fn taken_and_missing_cells_are_rejected() {
+        let mut game = Mnk::new(GameId::new(), MnkOptions::default());
+        play_moves(&mut game, &[(1, 1)]);
+
+        assert!(game.place(1, 1).is_err());
+        assert!(game.place(3, 0).is_err());
+        assert!(game.place(0, 3).is_err());
+    }
+
+    #[test]
+    fn full_board_without_line_is_drawn() {
+        let mut game = Mnk::new(GameId::new(), MnkOptions::default());
+        play_moves(
+            &mut game,
+            &[
+                (0, 0),
+                (0, 1),
+                (0, 2),
+                (1, 1),
+                (1, 0),
+                (1, 2),
+                (2, 1),
+                (2, 0),
+                (2, 2),
+            ],
+        );
+
+        assert!(game.is_full());
+        for row in 0..3 {
+            for column in 0..3 {
+                assert!(!game.winning_move(row, column));
+            }
+        }
+    }
+
+    #[test]
+    fn gomoku_needs_five_in_a_row() {
+        let options = MnkOptions {
+            rows: 15,
+            columns: 15,
+            connect: 5,
+        };
+        let mut game = Mnk::new(GameId::new(), options);
+        play_moves(
+            &mut game,
+            &[(7, 3), (0, 0), (7, 4), (0, 1), (7, 5), (0, 2), (7, 6)],
+        );
+
+        assert!(!game.winning_move(7, 6));
+        play_moves(&mut game, &[(0, 3), (7, 7)]);
+        assert!(game.winning_move(7, 7));
+    }
+}
@@ -1,12 +1,30 @@
 pub mod adapter;
+pub mod battleship;
+pub mod chess;
 pub mod connect4;
+pub mod hearts;
+pub mod history;
+pub mod iterated;
+pub mod lines;
+pub mod matchmaking;
+pub mod mnk;
+pub mod othello;
+pub mod rating;
+pub mod registry;
 pub mod search;
 pub mod snake;
+pub mod storage;
+pub mod tournament;
 
 use crate::game::adapter::{
     GameAdapter, GameAdapterError, GameAdapterErrorType, GenericGameMove, GenericGameState, Stage,
+    Termination,
 };
+use crate::game::history::{History, MoveRecord};
+use crate::game::rating::{LeaderboardEntry, RatingTable};
+use crate::game::registry::GameInfo;
 use crate::game::search::{GameSummary, SearchEngine, SearchOptions};
+use crate::game::storage::{Storage, StoredGame};
 use crate::notify::Subscription;
 use actix_web::http::StatusCode;
 use actix_web::{ResponseError, Result};
@@ -17,141 +35,153 @@ use derive_more::Display;
 use rand::Rng;
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
-use std::ops::DerefMut;
-use std::sync::Mutex;
-
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
-pub enum GameType {
-    #[serde(rename = "connect_4")]
-    Connect4,
-    #[serde(rename = "snake")]
-    Snake,
-}
-
-fn encode_id(bytes: &[u8]) -> String {
-    base32::encode(base32::Alphabet::RFC4648 { padding: false }, bytes)
-}
-
-fn decode_id(data: &str) -> Option<Vec<u8>> {
-    base32::decode(base32::Alphabet::RFC4648 { padding: false }, data)
-}
-
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Display)]
-#[display(fmt = "game_{}", "encode_id(_0)")]
-pub struct GameId([u8; 4]);
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Display)]
-#[display(fmt = "session_{}", "encode_id(_0)")]
-pub struct SessionId([u8; 16]);
+/// The type of a game, which is always one of the games in the registry.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Display)]
+pub struct GameType(&'static str);
 
-fn new_parse_id_error<E>(id: &str) -> E
-where
-    E: de::Error,
-{
-    de::Error::custom(format!("invalid id: {}", id))
-}
-
-fn validate_id<E>(id: &str, prefix: &str) -> Result<Vec<u8>, E>
-where
-    E: de::Error,
-{
-    if !id.starts_with(prefix) {
-        return Err(new_parse_id_error(id));
+impl GameType {
+    pub fn find(game_type: &str) -> Option<GameType> {
+        registry::find(game_type).map(|x| GameType(x.game_type))
     }
 
-    decode_id(&id[prefix.len()..]).ok_or(new_parse_id_error(id))
-}
-
-impl GameId {
-    pub fn new() -> Self {
-        GameId(rand::thread_rng().gen())
+    pub fn info(self) -> &'static GameInfo {
+        registry::find(self.0).expect("game types are only created from the registry")
     }
 }
 
-impl Serialize for GameId {
+impl Serialize for GameType {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        serializer.serialize_str(&self.to_string())
+        serializer.serialize_str(self.0)
     }
 }
 
-impl<'de> Deserialize<'de> for GameId {
-    fn deserialize<D>(deserializer: D) -> Result<GameId, D::Error>
+impl<'de> Deserialize<'de> for GameType {
+    fn deserialize<D>(deserializer: D) -> Result<GameType, D::Error>
     where
         D: Deserializer<'de>,
     {
-        struct GameIdVisitor;
+        struct GameTypeVisitor;
 
-        impl<'de> de::Visitor<'de> for GameIdVisitor {
-            type Value = GameId;
+        impl<'de> de::Visitor<'de> for GameTypeVisitor {
+            type Value = GameType;
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("game id")
+                formatter.write_str("game type")
             }
 
-            fn visit_str<E>(self, v: &str) -> Result<GameId, E>
+            fn visit_str<E>(self, v: &str) -> Result<GameType, E>
             where
                 E: de::Error,
             {
-                let vec = validate_id(v, "game_")?;
-                let bytes = TryInto::<[u8; 4]>::try_into(vec).map_err(|_| new_parse_id_error(v))?;
-                Ok(GameId(bytes))
+                GameType::find(v)
+                    .ok_or_else(|| de::Error::custom(format!("unknown game type: {}", v)))
             }
         }
 
-        deserializer.deserialize_string(GameIdVisitor)
+        deserializer.deserialize_string(GameTypeVisitor)
     }
 }
 
-impl SessionId {
-    pub fn new() -> Self {
-        SessionId(rand::thread_rng().gen())
-    }
+fn encode_id(bytes: &[u8]) -> String {
+    base32::encode(base32::Alphabet::RFC4648 { padding: false }, bytes)
 }
 
-impl Serialize for SessionId {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        serializer.serialize_str(&self.to_string())
+fn decode_id(data: &str) -> Option<Vec<u8>> {
+    base32::decode(base32::Alphabet::RFC4648 { padding: false }, data)
+}
+
+fn new_parse_id_error<E>(id: &str) -> E
+where
+    E: de::Error,
+{
+    de::Error::custom(format!("invalid id: {}", id))
+}
+
+fn validate_id<E>(id: &str, prefix: &str) -> Result<Vec<u8>, E>
+where
+    E: de::Error,
+{
+    if !id.starts_with(prefix) {
+        return Err(new_parse_id_error(id));
     }
+
+    decode_id(&id[prefix.len()..]).ok_or(new_parse_id_error(id))
 }
 
-impl<'de> Deserialize<'de> for SessionId {
-    fn deserialize<D>(deserializer: D) -> Result<SessionId, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        struct SessionIdVisitor;
+/// Defines a random ID that is represented as a prefix followed by its bytes in base 32.
+macro_rules! random_id {
+    ($name:ident, $length:expr, $prefix:expr, $description:expr) => {
+        #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+        pub struct $name([u8; $length]);
 
-        impl<'de> de::Visitor<'de> for SessionIdVisitor {
-            type Value = SessionId;
+        impl $name {
+            pub fn new() -> Self {
+                $name(rand::thread_rng().gen())
+            }
+        }
 
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("session id")
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}{}", $prefix, encode_id(&self.0))
             }
+        }
 
-            fn visit_str<E>(self, v: &str) -> Result<SessionId, E>
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
             where
-                E: de::Error,
+                S: Serializer,
             {
-                let vec = validate_id(v, "session_")?;
-                let bytes =
-                    TryInto::<[u8; 16]>::try_into(vec).map_err(|_| new_parse_id_error(v))?;
-                Ok(SessionId(bytes))
+                serializer.serialize_str(&self.to_string())
             }
         }
 
-        deserializer.deserialize_string(SessionIdVisitor)
-    }
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<$name, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                struct IdVisitor;
+
+                impl<'de> de::Visitor<'de> for IdVisitor {
+                    type Value = $name;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                        formatter.write_str($description)
+                    }
+
+                    fn visit_str<E>(self, v: &str) -> Result<$name, E>
+                    where
+                        E: de::Error,
+                    {
+                        let vec = validate_id(v, $prefix)?;
+                        let bytes = TryInto::<[u8; $length]>::try_into(vec)
+                            .map_err(|_| new_parse_id_error(v))?;
+                        Ok($name(bytes))
+                    }
+                }
+
+                deserializer.deserialize_string(IdVisitor)
+            }
+        }
+    };
 }
 
+random_id!(GameId, 4, "game_", "game id");
+random_id!(SessionId, 16, "session_", "session id");
+random_id!(TournamentId, 4, "tournament_", "tournament id");
+random_id!(JoinToken, 8, "invite_", "join token");
+
 const MAX_USERNAME_LENGTH: usize = 12;
+const MIN_MOVE_TIMEOUT_MS: u64 = 100;
 
 #[derive(Debug, Clone, Display)]
 pub enum InvalidUsernameReason {
@@ -163,6 +193,21 @@ pub enum InvalidUsernameReason {
     TooLong,
 }
 
+pub fn validate_username(username: &str) -> Result<()> {
+    let reason = if username.is_empty() {
+        InvalidUsernameReason::TooShort
+    } else if username.len() > MAX_USERNAME_LENGTH {
+        InvalidUsernameReason::TooLong
+    } else {
+        return Ok(());
+    };
+
+    Err(actix_web::Error::from(GameManagerError::InvalidUsername {
+        username: username.to_string(),
+        reason,
+    }))
+}
+
 #[derive(Debug, Clone, Display)]
 pub enum GameManagerError {
     #[display(fmt = "no game with id {}", _0)]
@@ -176,6 +221,14 @@ pub enum GameManagerError {
     },
     #[display(fmt = "page must be at least one")]
     InvalidPage,
+    #[display(fmt = "no state at clock {}", _0)]
+    InvalidClock(usize),
+    #[display(fmt = "move timeout must be at least {} ms", MIN_MOVE_TIMEOUT_MS)]
+    InvalidMoveTimeout,
+    #[display(fmt = "a valid join token is needed to join game {}", _0)]
+    InvalidJoinToken(GameId),
+    #[display(fmt = "move payload must have game_type {}", _0)]
+    WrongGameType(GameType),
 }
 
 impl ResponseError for GameManagerError {
@@ -183,11 +236,13 @@ impl ResponseError for GameManagerError {
         match self {
             GameManagerError::GameNotFound(_) => StatusCode::NOT_FOUND,
             GameManagerError::SessionNotFound(_) => StatusCode::NOT_FOUND,
+            GameManagerError::InvalidJoinToken(_) => StatusCode::FORBIDDEN,
             _ => StatusCode::BAD_REQUEST,
         }
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Session {
     username: String,
 }
@@ -198,48 +253,193 @@ impl Session {
     }
 }
 
+/// Options chosen when a game is created that are enforced by the game manager.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct GameSettings {
+    pub move_timeout_ms: Option<u64>,
+    pub join_token: Option<JoinToken>,
+}
+
+impl GameSettings {
+    /// Private games are not listed and can only be joined with their join token.
+    pub fn is_private(&self) -> bool {
+        self.join_token.is_some()
+    }
+}
+
 pub struct Game {
+    game_type: GameType,
     adapter: Box<dyn GameAdapter>,
     sessions: HashMap<SessionId, Session>,
     last_update: DateTime<Utc>,
+    history: History,
+    rated: bool,
+    settings: GameSettings,
+    deadline: Option<DateTime<Utc>>,
+    // The players that could move when the current deadline was set
+    turn: Vec<String>,
+    termination: Option<Termination>,
+    draw_offers: Vec<String>,
+}
+
+impl Game {
+    fn encode_state(&self, viewer: Option<&str>) -> Result<GenericGameState> {
+        let mut state =
+            GameManager::encode_state(self.game_type, self.adapter.deref(), viewer, self.deadline)?;
+        state.termination = self.termination.clone();
+        state.draw_offers = self.draw_offers.clone();
+        Ok(state)
+    }
+}
+
+/// Actions that any game in progress accepts in place of a move.
+#[derive(Deserialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum GameAction {
+    Resign,
+    OfferDraw,
+    AcceptDraw,
+}
+
+#[derive(Serialize)]
+pub struct GameUpdate {
+    pub clock: usize,
+    pub state: GenericGameState,
 }
 
 pub struct GameManager {
     games: DashMap<GameId, Mutex<Game>>,
+    ratings: RatingTable,
+    storage: Arc<dyn Storage>,
+    // The games due to tick at each time, which are real-time games with their next tick scheduled
+    ticks: Mutex<BTreeMap<DateTime<Utc>, Vec<GameId>>>,
+    tick_scheduled: Notify,
 }
 
 impl GameManager {
-    pub fn new() -> Self {
-        GameManager {
-            games: DashMap::new(),
+    pub fn new(storage: Arc<dyn Storage>) -> std::io::Result<Self> {
+        let games = DashMap::new();
+
+        for stored in storage.load_games()? {
+            match stored.game_type.info().load_adapter(stored.adapter) {
+                Ok(adapter) => {
+                    adapter.get_notifier().restore_clock(stored.clock);
+                    games.insert(
+                        stored.game_id,
+                        Mutex::new(Game {
+                            game_type: stored.game_type,
+                            adapter,
+                            sessions: stored.sessions,
+                            last_update: stored.last_update,
+                            history: stored.history,
+                            rated: stored.rated,
+                            settings: stored.settings,
+                            deadline: stored.deadline,
+                            turn: stored.turn,
+                            termination: stored.termination,
+                            draw_offers: stored.draw_offers,
+                        }),
+                    );
+                }
+                Err(e) => log::warn!("skipping unloadable game {}: {}", stored.game_id, e),
+            }
         }
+
+        let manager = GameManager {
+            games,
+            ratings: RatingTable::new(storage.load_ratings()?),
+            storage,
+            ticks: Mutex::new(BTreeMap::new()),
+            tick_scheduled: Notify::new(),
+        };
+        for entry in manager.games.iter() {
+            let game = entry.value().lock().unwrap();
+            if let (Some(_), Some(tick)) = (game.adapter.get_tick_interval_ms(), game.deadline) {
+                manager.schedule_tick(*entry.key(), tick);
+            }
+        }
+
+        Ok(manager)
     }
 
     pub fn create_game(
         &self,
-        factory: impl FnOnce(GameId) -> Box<dyn GameAdapter>,
+        game_type: GameType,
+        options: Value,
+        settings: GameSettings,
     ) -> Result<GameId> {
+        if settings
+            .move_timeout_ms
+            .is_some_and(|x| x < MIN_MOVE_TIMEOUT_MS)
+        {
+            return Err(actix_web::Error::from(GameManagerError::InvalidMoveTimeout));
+        }
+
         self.gc_games();
 
         loop {
             let game_id = GameId::new();
             if let entry @ Entry::Vacant(_) = self.games.entry(game_id) {
-                entry.or_insert(Mutex::new(Game {
-                    adapter: factory(game_id),
+                let mut game = Game {
+                    game_type,
+                    adapter: game_type.info().new_adapter(game_id, options)?,
                     sessions: HashMap::new(),
                     last_update: chrono::offset::Utc::now(),
-                }));
+                    history: History::default(),
+                    rated: false,
+                    settings,
+                    deadline: None,
+                    turn: vec![],
+                    termination: None,
+                    draw_offers: vec![],
+                };
+                self.record_update(game_id, &mut game, false);
+                entry.or_insert(Mutex::new(game));
                 break Ok(game_id);
             }
         }
     }
 
-    pub fn receive_join(&self, game_id: GameId, username: String) -> Result<SessionId> {
+    pub fn receive_join(
+        &self,
+        game_id: GameId,
+        username: String,
+        join_token: Option<JoinToken>,
+    ) -> Result<SessionId> {
+        self.join(game_id, username, join_token, None)
+    }
+
+    /// Joins a player to a game with a session chosen by the caller, such as a tournament.
+    pub fn assign_seat(
+        &self,
+        game_id: GameId,
+        username: String,
+        session_id: SessionId,
+    ) -> Result<()> {
+        self.join(game_id, username, None, Some(session_id))
+            .map(|_| ())
+    }
+
+    fn join(
+        &self,
+        game_id: GameId,
+        username: String,
+        join_token: Option<JoinToken>,
+        session_id: Option<SessionId>,
+    ) -> Result<SessionId> {
         let mutex = self
             .games
             .get(&game_id)
             .ok_or_else(|| GameManager::game_not_found(game_id))?;
         let mut mutex_guard = mutex.lock().unwrap();
+
+        let required_token = mutex_guard.settings.join_token;
+        if required_token.is_some() && join_token != required_token {
+            return Err(actix_web::Error::from(GameManagerError::InvalidJoinToken(
+                game_id,
+            )));
+        }
+
         let game_adapter = mutex_guard.adapter.deref_mut();
 
         if game_adapter.get_stage() != Stage::Waiting {
@@ -249,19 +449,7 @@ impl GameManager {
             ));
         }
 
-        if username.is_empty() {
-            return Err(actix_web::Error::from(GameManagerError::InvalidUsername {
-                username,
-                reason: InvalidUsernameReason::TooShort,
-            }));
-        }
-
-        if username.len() > MAX_USERNAME_LENGTH {
-            return Err(actix_web::Error::from(GameManagerError::InvalidUsername {
-                username,
-                reason: InvalidUsernameReason::TooLong,
-            }));
-        }
+        validate_username(&username)?;
 
         if game_adapter.has_player(&username) {
             return Err(actix_web::Error::from(GameManagerError::InvalidUsername {
@@ -271,17 +459,20 @@ impl GameManager {
         }
 
         game_adapter.add_player(username.clone())?;
-        mutex_guard.last_update = chrono::offset::Utc::now();
 
-        let new_session = Session::new(username);
-        loop {
+        let session_id = session_id.unwrap_or_else(|| loop {
             let session_id = SessionId::new();
 
             if !mutex_guard.sessions.contains_key(&session_id) {
-                mutex_guard.sessions.insert(session_id, new_session);
-                return Ok(session_id);
+                break session_id;
             }
-        }
+        });
+        mutex_guard
+            .sessions
+            .insert(session_id, Session::new(username));
+        self.record_update(game_id, &mut mutex_guard, false);
+
+        Ok(session_id)
     }
 
     pub fn receive_move(
@@ -302,35 +493,201 @@ impl GameManager {
             .username
             .clone();
 
-        mutex_guard.adapter.deref_mut().play_move(GenericGameMove {
+        // Moves name their game type so that clients cannot send a move meant for another game
+        if encoded_move.get("game_type").and_then(Value::as_str) != Some(mutex_guard.game_type.0) {
+            return Err(actix_web::Error::from(GameManagerError::WrongGameType(
+                mutex_guard.game_type,
+            )));
+        }
+
+        let action = encoded_move
+            .get("action")
+            .and_then(|x| GameAction::deserialize(x).ok());
+        let clock = mutex_guard.adapter.get_notifier().clock();
+        if let Some(action) = action {
+            GameManager::play_action(game_id, &mut mutex_guard, &username, action)?;
+        } else {
+            let can_move = mutex_guard.adapter.get_encoded_state()?.can_move;
+            mutex_guard.adapter.deref_mut().play_move(GenericGameMove {
+                player: username.clone(),
+                payload: encoded_move.clone(),
+            })?;
+            // Playing on declines a draw offered by the other players, but moves that do not
+            // change who can move, such as proposing a takeback, leave it open
+            if mutex_guard.adapter.get_encoded_state()?.can_move != can_move
+                && !mutex_guard.draw_offers.contains(&username)
+            {
+                mutex_guard.draw_offers.clear();
+            }
+            // A player who can move again straight away, such as after the opponent passes, gets
+            // a new deadline for that move
+            mutex_guard.turn.retain(|x| *x != username);
+        }
+
+        // The move is recorded before the update so that it is persisted along with it
+        let record = MoveRecord {
             player: username,
             payload: encoded_move,
-        })?;
-        mutex_guard.last_update = chrono::offset::Utc::now();
+            timestamp: chrono::offset::Utc::now(),
+            clock: mutex_guard.adapter.get_notifier().clock(),
+        };
+        if record.clock == clock {
+            // Nothing changed that players can see, such as a direction that takes effect at the
+            // next tick of a real-time game, so the move is persisted with the next update
+            mutex_guard.history.record_pending_move(record);
+            return Ok(());
+        }
+        mutex_guard.history.record_move(record);
+        // Actions are handled by the manager rather than the adapter, so they cannot be replayed
+        self.record_update(game_id, &mut mutex_guard, action.is_none());
 
         Ok(())
     }
 
-    pub fn get_state(&self, game_id: GameId) -> Result<GenericGameState> {
+    /// Resigns for a player, or offers or accepts a draw, which every game supports in the same way.
+    /// A resigning player places last, with every other player still in contention winning, and a
+    /// draw is agreed once every player still in contention has offered or accepted it.
+    fn play_action(
+        game_id: GameId,
+        game: &mut Game,
+        username: &str,
+        action: GameAction,
+    ) -> Result<()> {
+        let stage = game.adapter.get_stage();
+        if stage != Stage::InProgress {
+            return Err(GameAdapterError::actix_err(
+                game_id,
+                GameAdapterErrorType::InvalidGameStage(stage),
+            ));
+        }
+        let contenders = game.adapter.get_contenders()?;
+        if !contenders.iter().any(|x| x == username) {
+            return Err(GameAdapterError::actix_err(
+                game_id,
+                GameAdapterErrorType::InvalidMove(String::from(
+                    "players who are out of the game cannot resign or agree to a draw",
+                )),
+            ));
+        }
+
+        match action {
+            GameAction::Resign => {
+                let winners = contenders.into_iter().filter(|x| x != username).collect();
+                game.adapter.end_game(winners);
+                game.adapter.get_notifier().send();
+                game.termination = Some(Termination::Resignation {
+                    player: username.to_string(),
+                });
+                game.draw_offers.clear();
+            }
+            GameAction::OfferDraw | GameAction::AcceptDraw => {
+                let reason = if game.draw_offers.iter().any(|x| x == username) {
+                    Some("a draw has already been offered")
+                } else if action == GameAction::AcceptDraw && game.draw_offers.is_empty() {
+                    Some("no draw has been offered")
+                } else {
+                    None
+                };
+                if let Some(reason) = reason {
+                    return Err(GameAdapterError::actix_err(
+                        game_id,
+                        GameAdapterErrorType::InvalidMove(String::from(reason)),
+                    ));
+                }
+
+                game.draw_offers.push(username.to_string());
+                if contenders.iter().all(|x| game.draw_offers.contains(x)) {
+                    game.adapter.end_game(vec![]);
+                    game.adapter.get_notifier().send();
+                    game.termination = Some(Termination::DrawAgreed);
+                    game.draw_offers.clear();
+                } else {
+                    // The adapter state is unchanged, so let the other players know of the offer
+                    game.adapter.get_notifier().send();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the state of a game as seen by the player with the given session, or by a spectator.
+    pub fn get_state(
+        &self,
+        game_id: GameId,
+        session_id: Option<SessionId>,
+    ) -> Result<GenericGameState> {
+        let mutex = self
+            .games
+            .get(&game_id)
+            .ok_or_else(|| GameManager::game_not_found(game_id))?;
+        let mutex_guard = mutex.lock().unwrap();
+        let viewer = match session_id {
+            Some(session_id) => Some(
+                mutex_guard
+                    .sessions
+                    .get(&session_id)
+                    .ok_or_else(|| GameManager::session_not_found(session_id))?
+                    .username
+                    .as_str(),
+            ),
+            None => None,
+        };
+
+        mutex_guard.encode_state(viewer)
+    }
+
+    pub fn get_update(&self, game_id: GameId) -> Result<GameUpdate> {
         let mutex = self
             .games
             .get(&game_id)
             .ok_or_else(|| GameManager::game_not_found(game_id))?;
-        let mut mutex_guard = mutex.lock().unwrap();
-        let game_adapter = mutex_guard.adapter.deref_mut();
+        let mutex_guard = mutex.lock().unwrap();
 
-        let mut state = game_adapter.get_encoded_state()?;
+        Ok(GameUpdate {
+            clock: mutex_guard.adapter.get_notifier().clock(),
+            state: mutex_guard.encode_state(None)?,
+        })
+    }
 
-        if let serde_json::Value::Object(ref mut map) = state.payload {
-            map.insert(
-                String::from("game_type"),
-                serde_json::to_value(game_adapter.get_type()).unwrap(),
-            );
+    pub fn get_history(&self, game_id: GameId) -> Result<Vec<MoveRecord>> {
+        let mutex = self
+            .games
+            .get(&game_id)
+            .ok_or_else(|| GameManager::game_not_found(game_id))?;
+        let mutex_guard = mutex.lock().unwrap();
+        let mut moves = mutex_guard.history.get_moves();
 
-            return Ok(state);
+        let adapter = mutex_guard.adapter.deref();
+        if adapter.has_private_moves() && adapter.get_stage() != Stage::Ended {
+            for record in moves.iter_mut() {
+                record.payload = serde_json::Value::Null;
+            }
         }
+        Ok(moves)
+    }
 
-        panic!("State payload must be a Serde object")
+    pub fn get_state_at(&self, game_id: GameId, clock: usize) -> Result<GameUpdate> {
+        let mutex = self
+            .games
+            .get(&game_id)
+            .ok_or_else(|| GameManager::game_not_found(game_id))?;
+        let mutex_guard = mutex.lock().unwrap();
+
+        let game_type = mutex_guard.game_type;
+        let (state_clock, game_adapter) = mutex_guard
+            .history
+            .state_at(game_type, clock)
+            .ok_or_else(|| actix_web::Error::from(GameManagerError::InvalidClock(clock)))??;
+        let mut state = GameManager::encode_state(game_type, game_adapter.deref(), None, None)?;
+        // A game only ends once, so every snapshot of it having ended shares its termination
+        if state.stage == Stage::Ended {
+            state.termination = mutex_guard.termination.clone();
+        }
+
+        Ok(GameUpdate {
+            clock: state_clock,
+            state,
+        })
     }
 
     pub fn list_games(&self, options: SearchOptions) -> Result<Vec<GameSummary>> {
@@ -342,18 +699,27 @@ impl GameManager {
                     game_adapter.get_encoded_state().unwrap();
                 GameSummary {
                     game_id: *x.key(),
-                    game_type: game_adapter.get_type(),
+                    game_type: guard.game_type,
                     players,
                     stage,
                     last_updated: guard.last_update,
+                    private: guard.settings.is_private(),
                 }
             }),
             &options,
         )
     }
 
+    pub fn get_leaderboard(&self, game_type: GameType) -> Vec<LeaderboardEntry> {
+        self.ratings.get_leaderboard(game_type)
+    }
+
+    /// Counts the games that are listed, which leaves out private games.
     pub fn get_number_of_games(&self) -> usize {
-        self.games.len()
+        self.games
+            .iter()
+            .filter(|x| !x.value().lock().unwrap().settings.is_private())
+            .count()
     }
 
     pub fn subscribe(&self, game_id: GameId) -> Result<Subscription> {
@@ -369,12 +735,236 @@ impl GameManager {
             .subscribe())
     }
 
+    /// Lets the adapter of every game whose move deadline has passed handle the timeout. Real-time
+    /// games are left to `run_due_ticks`.
+    pub fn expire_deadlines(&self) {
+        let now = chrono::offset::Utc::now();
+
+        for entry in self.games.iter() {
+            let mut mutex_guard = entry.value().lock().unwrap();
+            if mutex_guard.adapter.get_tick_interval_ms().is_none() {
+                self.expire_deadline(*entry.key(), &mut mutex_guard, now);
+            }
+        }
+    }
+
+    /// Waits until the next tick of a real-time game is due, or until an earlier one is scheduled.
+    pub async fn wait_for_tick(&self) {
+        let next_tick = self.ticks.lock().unwrap().keys().next().copied();
+        let scheduled = self.tick_scheduled.notified();
+
+        match next_tick {
+            Some(next_tick) => {
+                let wait = (next_tick - chrono::offset::Utc::now())
+                    .to_std()
+                    .unwrap_or_default();
+                let _ = tokio::time::timeout(wait, scheduled).await;
+            }
+            None => scheduled.await,
+        }
+    }
+
+    /// Ticks every real-time game whose next tick is due.
+    pub fn run_due_ticks(&self) {
+        let now = chrono::offset::Utc::now();
+        let due = {
+            let mut ticks = self.ticks.lock().unwrap();
+            let later = ticks.split_off(&(now + Duration::nanoseconds(1)));
+            std::mem::replace(&mut *ticks, later)
+        };
+
+        for game_id in due.into_values().flatten() {
+            if let Some(mutex) = self.games.get(&game_id) {
+                self.expire_deadline(game_id, &mut mutex.lock().unwrap(), now);
+            }
+        }
+    }
+
+    fn schedule_tick(&self, game_id: GameId, tick: DateTime<Utc>) {
+        let mut ticks = self.ticks.lock().unwrap();
+        let earliest = ticks.keys().next().is_none_or(|&x| tick < x);
+        ticks.entry(tick).or_default().push(game_id);
+
+        if earliest {
+            self.tick_scheduled.notify();
+        }
+    }
+
+    fn expire_deadline(&self, game_id: GameId, game: &mut Game, now: DateTime<Utc>) {
+        if game.deadline.is_none_or(|x| x > now) {
+            return;
+        }
+
+        // Only timeouts that were handled can be replayed
+        let replayable = match game.adapter.deref_mut().handle_timeout() {
+            Ok(()) => {
+                let clock = game.adapter.get_notifier().clock();
+                game.history.record_timeout(clock);
+                true
+            }
+            Err(e) => {
+                log::error!("failed to time out game {}: {}", game_id, e);
+                false
+            }
+        };
+
+        // Always start a new turn, even if the same players can move again. Real-time games
+        // keep to their schedule instead of counting from when the timeout was handled, but
+        // skip the ticks they have fallen behind on.
+        game.deadline = match game.adapter.get_tick_interval_ms() {
+            Some(interval) => game
+                .deadline
+                .map(|x| (x + Duration::milliseconds(interval as i64)).max(now)),
+            None => None,
+        };
+        self.record_update(game_id, game, replayable);
+    }
+
     fn gc_games(&self) {
         let now = chrono::offset::Utc::now();
-        self.games.retain(|_, v| match v.try_lock() {
-            Ok(guard) => guard.last_update + Duration::minutes(5) >= now,
-            Err(_) => true,
-        })
+        let mut removed = vec![];
+        self.games.retain(|&game_id, v| {
+            let retain = match v.try_lock() {
+                Ok(guard) => guard.last_update + Duration::minutes(5) >= now,
+                Err(_) => true,
+            };
+            if !retain {
+                removed.push(game_id);
+            }
+            retain
+        });
+
+        for game_id in removed {
+            if let Err(e) = self.storage.remove_game(game_id) {
+                log::error!("failed to remove game {} from storage: {}", game_id, e);
+            }
+        }
+    }
+
+    /// Stamps and persists a game after its state has changed. Updates that the history cannot
+    /// replay from the moves and timeouts it records are snapshotted, as is every so often one that
+    /// can.
+    fn record_update(&self, game_id: GameId, game: &mut Game, replayable: bool) {
+        let deadline = game.deadline;
+        game.last_update = chrono::offset::Utc::now();
+        GameManager::update_deadline(game);
+
+        if game.adapter.get_tick_interval_ms().is_some() && game.deadline != deadline {
+            if let Some(tick) = game.deadline {
+                self.schedule_tick(game_id, tick);
+            }
+        }
+
+        if !replayable || game.history.needs_snapshot() {
+            match game.adapter.save_state() {
+                Ok(state) => game
+                    .history
+                    .record_snapshot(game.adapter.get_notifier().clock(), state),
+                Err(e) => log::error!("failed to snapshot game {}: {}", game_id, e),
+            }
+        }
+
+        if !game.rated && game.adapter.get_stage() == Stage::Ended {
+            match game.adapter.get_encoded_state() {
+                Ok(GenericGameState {
+                    players, winners, ..
+                }) => {
+                    self.ratings.record_game(game.game_type, &players, &winners);
+                    if let Err(e) = self.ratings.save(self.storage.as_ref()) {
+                        log::error!("failed to persist ratings: {}", e);
+                    }
+                }
+                Err(e) => log::error!("failed to rate game {}: {}", game_id, e),
+            }
+            game.rated = true;
+        }
+
+        self.persist(game_id, game);
+    }
+
+    /// Sets a new move deadline whenever players that could not move before are able to, or the
+    /// time of the next tick for real-time games.
+    fn update_deadline(game: &mut Game) {
+        let can_move = match game.adapter.get_encoded_state() {
+            Ok(state) if state.stage == Stage::InProgress => state.can_move,
+            _ => vec![],
+        };
+
+        if let Some(interval) = game.adapter.get_tick_interval_ms() {
+            game.deadline = match game.adapter.get_stage() {
+                Stage::InProgress => game.deadline.or(Some(
+                    game.last_update + Duration::milliseconds(interval as i64),
+                )),
+                _ => None,
+            };
+            game.turn = can_move;
+            return;
+        }
+
+        game.deadline = match game.settings.move_timeout_ms {
+            Some(timeout) if !can_move.is_empty() => {
+                if game.deadline.is_none() || can_move.iter().any(|x| !game.turn.contains(x)) {
+                    Some(game.last_update + Duration::milliseconds(timeout as i64))
+                } else {
+                    game.deadline
+                }
+            }
+            _ => None,
+        };
+        game.turn = can_move;
+    }
+
+    fn persist(&self, game_id: GameId, game: &mut Game) {
+        let result = self
+            .storage
+            .append_history(game_id, game.history.get_unpersisted())
+            .map(|_| game.history.mark_persisted())
+            .map_err(|e| e.to_string())
+            .and_then(|_| game.adapter.save_state().map_err(|e| e.to_string()))
+            .and_then(|adapter| {
+                self.storage
+                    .save_game(&StoredGame {
+                        game_id,
+                        game_type: game.game_type,
+                        adapter,
+                        sessions: game.sessions.clone(),
+                        last_update: game.last_update,
+                        history: History::default(),
+                        rated: game.rated,
+                        clock: game.adapter.get_notifier().clock(),
+                        settings: game.settings.clone(),
+                        deadline: game.deadline,
+                        turn: game.turn.clone(),
+                        termination: game.termination.clone(),
+                        draw_offers: game.draw_offers.clone(),
+                    })
+                    .map_err(|e| e.to_string())
+            });
+
+        if let Err(e) = result {
+            log::error!("failed to persist game {}: {}", game_id, e);
+        }
+    }
+
+    fn encode_state(
+        game_type: GameType,
+        game_adapter: &dyn GameAdapter,
+        viewer: Option<&str>,
+        deadline: Option<DateTime<Utc>>,
+    ) -> Result<GenericGameState> {
+        let mut state = game_adapter.get_viewer_state(viewer)?;
+        state.deadline = deadline;
+
+        if let serde_json::Value::Object(ref mut map) = state.payload {
+            map.insert(
+                String::from("game_type"),
+                serde_json::to_value(game_type).unwrap(),
+            );
+
+            return Ok(state);
+        }
+
+        panic!("State payload must be a Serde object")
     }
 
     fn game_not_found(game_id: GameId) -> actix_web::Error {
@@ -385,3 +975,386 @@ impl GameManager {
         actix_web::Error::from(GameManagerError::SessionNotFound(session_id))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::search::{SortKey, SortOrder};
+    use crate::game::storage::{FileStorage, MemoryStorage};
+
+    fn create_game(manager: &GameManager) -> (GameId, SessionId, SessionId) {
+        let game_type = GameType::find("connect_4").unwrap();
+        let game_id = manager
+            .create_game(game_type, Value::Null, GameSettings::default())
+            .unwrap();
+        let a = manager
+            .receive_join(game_id, String::from("a"), None)
+            .unwrap();
+        let b = manager
+            .receive_join(game_id, String::from("b"), None)
+            .unwrap();
+        (game_id, a, b)
+    }
+
+    fn action(action: &str) -> Value {
+        serde_json::json!({ "game_type": "connect_4", "action": action })
+    }
+
+    #[test]
+    fn private_games_are_not_listed_and_need_their_join_token() {
+        let manager = GameManager::new(Arc::new(MemoryStorage)).unwrap();
+        let game_type = GameType::find("connect_4").unwrap();
+        let join_token = JoinToken::new();
+        let private = manager
+            .create_game(
+                game_type,
+                Value::Null,
+                GameSettings {
+                    move_timeout_ms: None,
+                    join_token: Some(join_token),
+                },
+            )
+            .unwrap();
+        let public = manager
+            .create_game(game_type, Value::Null, GameSettings::default())
+            .unwrap();
+
+        let listed = manager
+            .list_games(SearchOptions {
+                page: 1,
+                sort_order: SortOrder::Desc,
+                sort_key: SortKey::LastUpdated,
+                game_type: None,
+                players: None,
+                stage: None,
+            })
+            .unwrap();
+        assert_eq!(
+            listed.iter().map(|x| x.game_id).collect::<Vec<_>>(),
+            vec![public]
+        );
+        assert_eq!(manager.get_number_of_games(), 1);
+
+        assert!(manager
+            .receive_join(private, String::from("a"), None)
+            .is_err());
+        assert!(manager
+            .receive_join(private, String::from("a"), Some(JoinToken::new()))
+            .is_err());
+        assert!(manager
+            .receive_join(private, String::from("a"), Some(join_token))
+            .is_ok());
+    }
+
+    #[test]
+    fn resigning_ends_the_game_for_the_other_player() {
+        let manager = GameManager::new(Arc::new(MemoryStorage)).unwrap();
+        let (game_id, a, _) = create_game(&manager);
+
+        manager.receive_move(game_id, a, action("resign")).unwrap();
+
+        let state = manager.get_state(game_id, None).unwrap();
+        assert_eq!(state.stage, Stage::Ended);
+        assert_eq!(state.winners, vec![String::from("b")]);
+        assert_eq!(
+            state.termination,
+            Some(Termination::Resignation {
+                player: String::from("a")
+            })
+        );
+        assert!(manager.receive_move(game_id, a, action("resign")).is_err());
+    }
+
+    #[test]
+    fn draws_must_be_offered_and_accepted() {
+        let manager = GameManager::new(Arc::new(MemoryStorage)).unwrap();
+        let (game_id, a, b) = create_game(&manager);
+        let column = serde_json::json!({ "game_type": "connect_4", "column": 0 });
+
+        assert!(manager
+            .receive_move(game_id, b, action("accept_draw"))
+            .is_err());
+        manager
+            .receive_move(game_id, a, action("offer_draw"))
+            .unwrap();
+        assert!(manager
+            .receive_move(game_id, a, action("accept_draw"))
+            .is_err());
+        assert_eq!(
+            manager.get_state(game_id, None).unwrap().draw_offers,
+            vec![String::from("a")]
+        );
+
+        // The player offering a draw can still move, but the opponent playing on declines it
+        manager.receive_move(game_id, a, column.clone()).unwrap();
+        manager.receive_move(game_id, b, column).unwrap();
+        assert!(manager
+            .receive_move(game_id, a, action("accept_draw"))
+            .is_err());
+
+        manager
+            .receive_move(game_id, b, action("offer_draw"))
+            .unwrap();
+        manager
+            .receive_move(game_id, a, action("accept_draw"))
+            .unwrap();
+
+        let state = manager.get_state(game_id, None).unwrap();
+        assert_eq!(state.stage, Stage::Ended);
+        assert!(state.winners.is_empty());
+        assert_eq!(state.termination, Some(Termination::DrawAgreed));
+        assert!(state.draw_offers.is_empty());
+    }
+
+    #[test]
+    fn proposing_a_takeback_leaves_a_draw_offer_open() {
+        let manager = GameManager::new(Arc::new(MemoryStorage)).unwrap();
+        let (game_id, a, b) = create_game(&manager);
+        let column = serde_json::json!({ "game_type": "connect_4", "column": 0 });
+
+        manager.receive_move(game_id, a, column).unwrap();
+        manager
+            .receive_move(game_id, b, action("offer_draw"))
+            .unwrap();
+        manager
+            .receive_move(game_id, a, action("propose_takeback"))
+            .unwrap();
+        assert_eq!(
+            manager.get_state(game_id, None).unwrap().draw_offers,
+            vec![String::from("b")]
+        );
+
+        manager
+            .receive_move(game_id, a, action("accept_draw"))
+            .unwrap();
+        let state = manager.get_state(game_id, None).unwrap();
+        assert_eq!(state.termination, Some(Termination::DrawAgreed));
+    }
+
+    #[test]
+    fn games_for_more_than_two_players_can_be_resigned_or_drawn() {
+        let manager = GameManager::new(Arc::new(MemoryStorage)).unwrap();
+        let game_type = GameType::find("snake").unwrap();
+        let create_game = || {
+            let game_id = manager
+                .create_game(
+                    game_type,
+                    serde_json::json!({ "players": 3 }),
+                    GameSettings::default(),
+                )
+                .unwrap();
+            let sessions: Vec<_> = ["a", "b", "c"]
+                .iter()
+                .map(|x| {
+                    manager
+                        .receive_join(game_id, String::from(*x), None)
+                        .unwrap()
+                })
+                .collect();
+            (game_id, sessions)
+        };
+        let action = |action: &str| serde_json::json!({ "game_type": "snake", "action": action });
+
+        // A draw needs every player, so the game goes on until the last one accepts
+        let (game_id, sessions) = create_game();
+        manager
+            .receive_move(game_id, sessions[0], action("offer_draw"))
+            .unwrap();
+        manager
+            .receive_move(game_id, sessions[1], action("accept_draw"))
+            .unwrap();
+        assert_eq!(
+            manager.get_state(game_id, None).unwrap().stage,
+            Stage::InProgress
+        );
+        manager
+            .receive_move(game_id, sessions[2], action("accept_draw"))
+            .unwrap();
+        let state = manager.get_state(game_id, None).unwrap();
+        assert_eq!(state.termination, Some(Termination::DrawAgreed));
+        assert!(state.winners.is_empty());
+
+        // A player who resigns places last behind everyone else
+        let (game_id, sessions) = create_game();
+        manager
+            .receive_move(game_id, sessions[1], action("resign"))
+            .unwrap();
+        let state = manager.get_state(game_id, None).unwrap();
+        assert_eq!(state.stage, Stage::Ended);
+        assert_eq!(state.winners, vec!["a", "c"]);
+        assert_eq!(
+            state.termination,
+            Some(Termination::Resignation {
+                player: String::from("b")
+            })
+        );
+    }
+
+    #[test]
+    fn player_moving_again_after_a_pass_gets_a_new_deadline() {
+        let manager = GameManager::new(Arc::new(MemoryStorage)).unwrap();
+        let settings = GameSettings {
+            move_timeout_ms: Some(60_000),
+            ..GameSettings::default()
+        };
+        let game_type = GameType::find("othello").unwrap();
+        let options = serde_json::json!({ "size": 4 });
+        let game_id = manager.create_game(game_type, options, settings).unwrap();
+        let a = manager
+            .receive_join(game_id, String::from("a"), None)
+            .unwrap();
+        let b = manager
+            .receive_join(game_id, String::from("b"), None)
+            .unwrap();
+        let play = |session_id, row, column| {
+            let payload =
+                serde_json::json!({ "game_type": "othello", "row": row, "column": column });
+            manager.receive_move(game_id, session_id, payload).unwrap();
+        };
+
+        play(a, 0, 1);
+        play(b, 0, 2);
+        play(a, 2, 3);
+        let deadline = manager.get_state(game_id, None).unwrap().deadline.unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+
+        // The first player has no legal moves left, so the second player moves again
+        play(b, 0, 0);
+        let state = manager.get_state(game_id, None).unwrap();
+        assert_eq!(state.can_move, vec![String::from("b")]);
+        assert!(state.deadline.unwrap() > deadline);
+    }
+
+    fn create_real_time_game(manager: &GameManager) -> (GameId, SessionId) {
+        let game_type = GameType::find("snake").unwrap();
+        let options = serde_json::json!({ "players": 2, "tick_ms": 100 });
+        let game_id = manager
+            .create_game(game_type, options, GameSettings::default())
+            .unwrap();
+        let a = manager
+            .receive_join(game_id, String::from("a"), None)
+            .unwrap();
+        manager
+            .receive_join(game_id, String::from("b"), None)
+            .unwrap();
+        (game_id, a)
+    }
+
+    #[test]
+    fn real_time_games_tick_when_due_without_catching_up() {
+        actix_web::rt::System::new("ticks").block_on(async {
+            let manager = GameManager::new(Arc::new(MemoryStorage)).unwrap();
+            let (game_id, _) = create_real_time_game(&manager);
+            let clock = manager.get_update(game_id).unwrap().clock;
+            let deadline = manager.get_state(game_id, None).unwrap().deadline.unwrap();
+            assert_eq!(
+                manager.ticks.lock().unwrap().values().collect::<Vec<_>>(),
+                vec![&vec![game_id]]
+            );
+
+            while chrono::offset::Utc::now() < deadline {
+                manager.wait_for_tick().await;
+            }
+            manager.expire_deadlines();
+            assert_eq!(manager.get_update(game_id).unwrap().clock, clock);
+            manager.run_due_ticks();
+            assert_eq!(manager.get_update(game_id).unwrap().clock, clock + 1);
+            assert!(manager.get_state(game_id, None).unwrap().deadline.unwrap() > deadline);
+
+            // A game that has fallen behind ticks once and then keeps to the interval from now
+            let now = chrono::offset::Utc::now();
+            let mutex = manager.games.get(&game_id).unwrap();
+            let mut game = mutex.lock().unwrap();
+            game.deadline = Some(now - Duration::seconds(10));
+            manager.expire_deadline(game_id, &mut game, now);
+            assert_eq!(game.adapter.get_notifier().clock(), clock + 2);
+            assert_eq!(game.deadline, Some(now));
+        });
+    }
+
+    #[test]
+    fn real_time_directions_wait_for_the_next_tick() {
+        let manager = GameManager::new(Arc::new(MemoryStorage)).unwrap();
+        let (game_id, a) = create_real_time_game(&manager);
+        let clock = manager.get_update(game_id).unwrap().clock;
+
+        for direction in ["up", "left"] {
+            let payload = serde_json::json!({ "game_type": "snake", "direction": direction });
+            manager.receive_move(game_id, a, payload).unwrap();
+        }
+        assert_eq!(manager.get_update(game_id).unwrap().clock, clock);
+        let moves = manager.get_history(game_id).unwrap();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].payload["direction"], "left");
+
+        // The tick persists and snapshots the move along with it
+        let now = chrono::offset::Utc::now();
+        let mutex = manager.games.get(&game_id).unwrap();
+        let mut game = mutex.lock().unwrap();
+        game.deadline = Some(now);
+        manager.expire_deadline(game_id, &mut game, now);
+        assert!(game.history.get_unpersisted().is_empty());
+        assert_eq!(game.adapter.get_notifier().clock(), clock + 1);
+    }
+
+    #[test]
+    fn clock_history_and_past_states_survive_a_restart() {
+        let directory = tempfile::tempdir().unwrap();
+        let column =
+            |column: usize| serde_json::json!({ "game_type": "connect_4", "column": column });
+        let manager = GameManager::new(Arc::new(
+            FileStorage::new(directory.path().to_path_buf()).unwrap(),
+        ));
+        let manager = manager.unwrap();
+        let (game_id, a, b) = create_game(&manager);
+        manager.receive_move(game_id, a, column(0)).unwrap();
+        let clock = manager.get_update(game_id).unwrap().clock;
+        drop(manager);
+
+        let manager = GameManager::new(Arc::new(
+            FileStorage::new(directory.path().to_path_buf()).unwrap(),
+        ))
+        .unwrap();
+        assert_eq!(manager.get_update(game_id).unwrap().clock, clock);
+        manager.receive_move(game_id, b, column(1)).unwrap();
+
+        let clocks = manager
+            .get_history(game_id)
+            .unwrap()
+            .iter()
+            .map(|x| x.clock)
+            .collect::<Vec<_>>();
+        assert_eq!(clocks, vec![clock, clock + 1]);
+        let cells =
+            |clock| manager.get_state_at(game_id, clock).unwrap().state.payload["cells"][1].clone();
+        assert_eq!(cells(clock), serde_json::json!([]));
+        assert_eq!(cells(clock + 1), serde_json::json!(["b"]));
+    }
+
+    #[test]
+    fn deadlines_are_not_renewed_after_a_restart() {
+        let directory = tempfile::tempdir().unwrap();
+        let storage = || Arc::new(FileStorage::new(directory.path().to_path_buf()).unwrap());
+        let manager = GameManager::new(storage()).unwrap();
+        let settings = GameSettings {
+            move_timeout_ms: Some(60_000),
+            join_token: None,
+        };
+        let game_id = manager
+            .create_game(GameType::find("snake").unwrap(), Value::Null, settings)
+            .unwrap();
+        let sessions = ["a", "b", "c", "d"]
+            .iter()
+            .map(|x| manager.receive_join(game_id, x.to_string(), None).unwrap())
+            .collect::<Vec<_>>();
+        let deadline = manager.get_state(game_id, None).unwrap().deadline;
+        assert!(deadline.is_some());
+        drop(manager);
+
+        // The other snakes still have the rest of the time they had before the restart
+        let manager = GameManager::new(storage()).unwrap();
+        let payload = serde_json::json!({ "game_type": "snake", "direction": "up" });
+        manager.receive_move(game_id, sessions[0], payload).unwrap();
+        assert_eq!(manager.get_state(game_id, None).unwrap().deadline, deadline);
+    }
+}
@@ -0,0 +1,449 @@
+use crate::game::adapter::{
+    GameAdapter, GameAdapterError, GameAdapterErrorType, GenericGameMove, GenericGameState, Stage,
+};
+use crate::game::GameId;
+use crate::notify::Notifier;
+use actix_web::error::{InternalError, JsonPayloadError};
+use actix_web::HttpResponse;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::vec;
+use std::vec::Vec;
+
+pub const NUM_PLAYERS: usize = 2;
+const DEFAULT_SIZE: usize = 8;
+const MIN_SIZE: usize = 4;
+const MAX_SIZE: usize = 16;
+
+const DIRECTIONS: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+#[derive(Serialize, Deserialize)]
+pub struct OthelloAdapter {
+    game_id: GameId,
+    players: Vec<String>,
+    stage: Stage,
+    #[serde(skip, default = "Notifier::new")]
+    notifier: Notifier,
+    game: Othello,
+    winner: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct OthelloRequestPayload {
+    row: usize,
+    column: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+struct Position {
+    row: usize,
+    column: usize,
+}
+
+#[derive(Serialize)]
+struct OthelloResponsePayload<'a> {
+    cells: Vec<Vec<Option<&'a String>>>,
+    legal_moves: Vec<Position>,
+    discs: HashMap<&'a String, usize>,
+    passed: Option<&'a String>,
+    options: &'a OthelloOptions,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+struct OthelloOptions {
+    size: usize,
+}
+
+impl Default for OthelloOptions {
+    fn default() -> Self {
+        OthelloOptions { size: DEFAULT_SIZE }
+    }
+}
+
+impl OthelloOptions {
+    fn validate(&self) -> Result<(), String> {
+        if !(MIN_SIZE..=MAX_SIZE).contains(&self.size) || !self.size.is_multiple_of(2) {
+            Err(format!(
+                "size must be an even number between {} and {}",
+                MIN_SIZE, MAX_SIZE
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Othello {
+    game_id: GameId,
+    // Index of the player to move next, where the first player has the dark discs
+    turn: usize,
+    board: Vec<Vec<Option<usize>>>, // vector of rows, each holding the index of a player.
+    // The player that had no legal moves after the last move, if any
+    passed: Option<usize>,
+    options: OthelloOptions,
+}
+
+impl GameAdapter for OthelloAdapter {
+    fn new(game_id: GameId, options: Value) -> actix_web::Result<Self>
+    where
+        Self: Sized,
+    {
+        let options = serde_json::from_value::<Option<OthelloOptions>>(options)
+            .map_err(|e| e.to_string())
+            .and_then(|options| {
+                let options = options.unwrap_or_default();
+                options.validate()?;
+                Ok(options)
+            })
+            .map_err(|e| {
+                GameAdapterError::actix_err(game_id, GameAdapterErrorType::InvalidOptions(e))
+            })?;
+
+        Ok(OthelloAdapter {
+            game_id,
+            players: vec![],
+            stage: Stage::Waiting,
+            notifier: Notifier::new(),
+            game: Othello::new(game_id, options),
+            winner: vec![],
+        })
+    }
+
+    fn load_state(state: Value) -> actix_web::Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(serde_json::from_value(state)?)
+    }
+
+    fn save_state(&self) -> actix_web::Result<Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn get_notifier(&self) -> &Notifier {
+        &self.notifier
+    }
+
+    fn add_player(&mut self, username: String) -> actix_web::Result<()> {
+        assert!(self.players.len() < NUM_PLAYERS);
+        assert_eq!(self.stage, Stage::Waiting);
+
+        self.players.push(username);
+        if self.players.len() == NUM_PLAYERS {
+            self.stage = Stage::InProgress;
+        }
+        self.notifier.send();
+        Ok(())
+    }
+
+    fn has_player(&self, username: &str) -> bool {
+        self.players.iter().any(|s| s.eq(username))
+    }
+
+    fn play_move(&mut self, game_move: GenericGameMove) -> actix_web::Result<()> {
+        if self.stage != Stage::InProgress {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidGameStage(self.stage),
+            ));
+        }
+
+        let request_payload = serde_json::from_value::<OthelloRequestPayload>(game_move.payload)
+            .map_err(|e| {
+                InternalError::from_response(
+                    "",
+                    HttpResponse::BadRequest()
+                        .content_type("text/plain")
+                        .body(JsonPayloadError::Deserialize(e).to_string()),
+                )
+            })?;
+
+        if self.players[self.game.turn] != game_move.player {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidPlayer(game_move.player),
+            ));
+        }
+
+        self.game
+            .place(request_payload.row, request_payload.column)?;
+
+        if !self.game.advance_turn() {
+            let [first, second] = self.game.disc_counts();
+            if first != second {
+                let winner = if first > second { 0 } else { 1 };
+                self.winner.push(self.players[winner].clone());
+            }
+            self.stage = Stage::Ended;
+        }
+        self.notifier.send();
+        Ok(())
+    }
+
+    fn handle_timeout(&mut self) -> actix_web::Result<()> {
+        if self.stage != Stage::InProgress {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidGameStage(self.stage),
+            ));
+        }
+
+        // The player who ran out of time forfeits
+        self.winner = vec![self.players[1 - self.game.turn].clone()];
+        self.stage = Stage::Ended;
+        self.notifier.send();
+        Ok(())
+    }
+
+    fn end_game(&mut self, winners: Vec<String>) {
+        self.winner = winners;
+        self.stage = Stage::Ended;
+    }
+
+    fn get_stage(&self) -> Stage {
+        self.stage
+    }
+
+    fn get_encoded_state(&self) -> actix_web::Result<GenericGameState> {
+        // The starting discs are on the board before both players have joined
+        let cells = self
+            .game
+            .board
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| cell.and_then(|player| self.players.get(player)))
+                    .collect()
+            })
+            .collect();
+        let in_progress = self.stage == Stage::InProgress;
+
+        let response_payload = OthelloResponsePayload {
+            cells,
+            legal_moves: if in_progress {
+                self.game.legal_moves(self.game.turn)
+            } else {
+                vec![]
+            },
+            discs: self.players.iter().zip(self.game.disc_counts()).collect(),
+            passed: self.game.passed.map(|player| &self.players[player]),
+            options: &self.game.options,
+        };
+
+        Ok(GenericGameState {
+            players: self.players.clone(),
+            stage: self.stage,
+            can_move: if in_progress {
+                vec![self.players[self.game.turn].clone()]
+            } else {
+                vec![]
+            },
+            winners: self.winner.clone(),
+            deadline: None,
+            termination: None,
+            draw_offers: vec![],
+            payload: serde_json::to_value(&response_payload)?,
+        })
+    }
+}
+
+impl Othello {
+    fn new(game_id: GameId, options: OthelloOptions) -> Self {
+        let size = options.size;
+        let mut board = vec![vec![None; size]; size];
+        let middle = size / 2;
+        board[middle - 1][middle - 1] = Some(1);
+        board[middle][middle] = Some(1);
+        board[middle - 1][middle] = Some(0);
+        board[middle][middle - 1] = Some(0);
+
+        Othello {
+            game_id,
+            turn: 0,
+            board,
+            passed: None,
+            options,
+        }
+    }
+
+    fn get_cell_at(&self, row: isize, column: isize) -> Option<Option<usize>> {
+        if row < 0 || column < 0 {
+            return None;
+        }
+        self.board.get(row as usize)?.get(column as usize).copied()
+    }
+
+    /// The opponent's discs that would be flipped if `player` placed a disc at the given cell.
+    fn flips(&self, player: usize, row: usize, column: usize) -> Vec<Position> {
+        if self.board[row][column].is_some() {
+            return vec![];
+        }
+
+        let mut flips = vec![];
+        for (row_step, column_step) in DIRECTIONS {
+            let mut line = vec![];
+            let (mut r, mut c) = (row as isize + row_step, column as isize + column_step);
+
+            while self.get_cell_at(r, c) == Some(Some(1 - player)) {
+                line.push(Position {
+                    row: r as usize,
+                    column: c as usize,
+                });
+                r += row_step;
+                c += column_step;
+            }
+
+            if self.get_cell_at(r, c) == Some(Some(player)) {
+                flips.extend(line);
+            }
+        }
+
+        flips
+    }
+
+    fn legal_moves(&self, player: usize) -> Vec<Position> {
+        let size = self.options.size;
+
+        (0..size)
+            .flat_map(|row| (0..size).map(move |column| Position { row, column }))
+            .filter(|x| !self.flips(player, x.row, x.column).is_empty())
+            .collect()
+    }
+
+    fn place(&mut self, row: usize, column: usize) -> actix_web::Result<()> {
+        let invalid_move = |reason: String| {
+            GameAdapterError::actix_err(self.game_id, GameAdapterErrorType::InvalidMove(reason))
+        };
+
+        if row >= self.options.size || column >= self.options.size {
+            return Err(invalid_move(format!(
+                "cell ({}, {}) does not exist",
+                row, column
+            )));
+        }
+
+        let flips = self.flips(self.turn, row, column);
+        if flips.is_empty() {
+            return Err(invalid_move(format!(
+                "a disc at ({}, {}) would not flip any discs",
+                row, column
+            )));
+        }
+
+        self.board[row][column] = Some(self.turn);
+        for Position { row, column } in flips {
+            self.board[row][column] = Some(self.turn);
+        }
+
+        Ok(())
+    }
+
+    /// Gives the turn to the next player that has a legal move, passing over a player that has
+    /// none. Returns false if neither player can move, which ends the game.
+    fn advance_turn(&mut self) -> bool {
+        let next = 1 - self.turn;
+
+        if !self.legal_moves(next).is_empty() {
+            self.turn = next;
+            self.passed = None;
+            true
+        } else if !self.legal_moves(self.turn).is_empty() {
+            self.passed = Some(next);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn disc_counts(&self) -> [usize; NUM_PLAYERS] {
+        let mut counts = [0; NUM_PLAYERS];
+        for player in self.board.iter().flatten().flatten() {
+            counts[*player] += 1;
+        }
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_game() -> Othello {
+        Othello::new(GameId::new(), OthelloOptions::default())
+    }
+
+    fn positions(cells: &[(usize, usize)]) -> Vec<Position> {
+        cells
+            .iter()
+            .map(|&(row, column)| Position { row, column })
+            .collect()
+    }
+
+    #[test]
+    fn opening_has_four_legal_moves() {
+        let game = create_game();
+
+        assert_eq!(
+            game.legal_moves(0),
+            positions(&[(2, 3), (3, 2), (4, 5), (5, 4)])
+        );
+        assert_eq!(game.disc_counts(), [2, 2]);
+    }
+
+    #[test]
+    fn placing_flips_enclosed_discs() {
+        let mut game = create_game();
+
+        game.place(2, 3).unwrap();
+
+        assert_eq!(game.board[3][3], Some(0));
+        assert_eq!(game.disc_counts(), [4, 1]);
+        assert!(game.advance_turn());
+        assert_eq!(game.turn, 1);
+    }
+
+    #[test]
+    fn moves_that_flip_nothing_are_rejected() {
+        let mut game = create_game();
+
+        assert!(game.place(0, 0).is_err());
+        assert!(game.place(3, 3).is_err());
+        assert!(game.place(8, 0).is_err());
+    }
+
+    #[test]
+    fn player_without_moves_passes() {
+        let mut game = create_game();
+        game.board = vec![vec![None; 8]; 8];
+        game.board[0][0] = Some(1);
+        game.board[0][1] = Some(0);
+        game.turn = 1;
+
+        // Dark has no moves, so light moves again
+        assert!(game.advance_turn());
+        assert_eq!(game.turn, 1);
+        assert_eq!(game.passed, Some(0));
+    }
+
+    #[test]
+    fn game_ends_when_nobody_can_move() {
+        let mut game = create_game();
+        game.board = vec![vec![Some(0); 8]; 8];
+        game.board[0][0] = None;
+
+        assert!(!game.advance_turn());
+        assert_eq!(game.disc_counts(), [63, 0]);
+    }
+}
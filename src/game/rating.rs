@@ -0,0 +1,178 @@
+use crate::game::storage::Storage;
+use crate::game::GameType;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::sync::Mutex;
+
+const INITIAL_RATING: f64 = 1500.0;
+const K_FACTOR: f64 = 32.0;
+const ELO_SCALE: f64 = 400.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rating {
+    pub rating: f64,
+    pub games_played: usize,
+    pub wins: usize,
+}
+
+impl Rating {
+    fn new() -> Self {
+        Rating {
+            rating: INITIAL_RATING,
+            games_played: 0,
+            wins: 0,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct LeaderboardEntry {
+    pub username: String,
+    #[serde(flatten)]
+    pub rating: Rating,
+}
+
+pub type StoredRatings = HashMap<GameType, HashMap<String, Rating>>;
+
+pub struct RatingTable {
+    ratings: Mutex<StoredRatings>,
+}
+
+fn expected_score(rating: f64, opponent_rating: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent_rating - rating) / ELO_SCALE))
+}
+
+/// Computes the rating change of every player from pairwise Elo comparisons, where winners
+/// beat non-winners and players in the same group draw. With two players this is plain Elo.
+fn rating_changes(ratings: &[f64], won: &[bool]) -> Vec<f64> {
+    let k = K_FACTOR / (ratings.len() - 1) as f64;
+
+    (0..ratings.len())
+        .map(|i| {
+            k * (0..ratings.len())
+                .filter(|&j| j != i)
+                .map(|j| {
+                    let score = match (won[i], won[j]) {
+                        (true, false) => 1.0,
+                        (false, true) => 0.0,
+                        _ => 0.5,
+                    };
+                    score - expected_score(ratings[i], ratings[j])
+                })
+                .sum::<f64>()
+        })
+        .collect()
+}
+
+impl RatingTable {
+    pub fn new(ratings: StoredRatings) -> Self {
+        RatingTable {
+            ratings: Mutex::new(ratings),
+        }
+    }
+
+    pub fn record_game(&self, game_type: GameType, players: &[String], winners: &[String]) {
+        let mut guard = self.ratings.lock().unwrap();
+
+        if players.len() >= 2 {
+            let table = guard.entry(game_type).or_default();
+            let won = players.iter().map(|x| winners.contains(x)).collect_vec();
+            let ratings = players
+                .iter()
+                .map(|x| table.get(x).map_or(INITIAL_RATING, |r| r.rating))
+                .collect_vec();
+
+            for ((player, change), won) in
+                players.iter().zip(rating_changes(&ratings, &won)).zip(won)
+            {
+                let rating = table.entry(player.clone()).or_insert_with(Rating::new);
+                rating.rating += change;
+                rating.games_played += 1;
+                if won {
+                    rating.wins += 1;
+                }
+            }
+        }
+    }
+
+    /// Saves the ratings while holding their lock, so that concurrent saves happen one at a time
+    /// and the last one to finish always has every update.
+    pub fn save(&self, storage: &dyn Storage) -> io::Result<()> {
+        storage.save_ratings(&self.ratings.lock().unwrap())
+    }
+
+    pub fn get_leaderboard(&self, game_type: GameType) -> Vec<LeaderboardEntry> {
+        self.ratings
+            .lock()
+            .unwrap()
+            .get(&game_type)
+            .map_or(vec![], |table| {
+                table
+                    .iter()
+                    .map(|(username, rating)| LeaderboardEntry {
+                        username: username.clone(),
+                        rating: rating.clone(),
+                    })
+                    .sorted_by(|a, b| b.rating.rating.total_cmp(&a.rating.rating))
+                    .collect()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn players(names: &[&str]) -> Vec<String> {
+        names.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn two_player_win_is_plain_elo() {
+        let changes = rating_changes(&[1500.0, 1500.0], &[true, false]);
+
+        assert_eq!(changes, vec![16.0, -16.0]);
+    }
+
+    #[test]
+    fn draw_between_equal_players_changes_nothing() {
+        let changes = rating_changes(&[1500.0, 1500.0, 1500.0], &[false, false, false]);
+
+        assert!(changes.iter().all(|&x| x == 0.0));
+    }
+
+    #[test]
+    fn multiplayer_changes_sum_to_zero() {
+        let changes = rating_changes(
+            &[1400.0, 1500.0, 1600.0, 1700.0],
+            &[true, false, false, true],
+        );
+
+        assert!(changes[0] > 0.0 && changes[1] < 0.0 && changes[2] < 0.0 && changes[3] > 0.0);
+        assert!(changes.iter().sum::<f64>().abs() < 1e-9);
+    }
+
+    #[test]
+    fn leaderboard_is_sorted_by_rating() {
+        let table = RatingTable::new(StoredRatings::new());
+        let all = players(&["a", "b", "c"]);
+        let snake = GameType::find("snake").unwrap();
+
+        table.record_game(snake, &all, &players(&["b"]));
+        table.record_game(snake, &all, &players(&["b", "c"]));
+
+        let leaderboard = table.get_leaderboard(snake);
+        let names = leaderboard
+            .iter()
+            .map(|x| x.username.as_str())
+            .collect_vec();
+        assert_eq!(names, vec!["b", "c", "a"]);
+        assert_eq!(leaderboard[0].rating.wins, 2);
+        assert_eq!(leaderboard[2].rating.games_played, 2);
+        assert!(table
+            .get_leaderboard(GameType::find("connect_4").unwrap())
+            .is_empty());
+    }
+}
@@ -0,0 +1,193 @@
+use crate::game::adapter::GameAdapter;
+use crate::game::{battleship, chess, connect4, hearts, iterated, mnk, othello, snake, GameId};
+use actix_web::Result;
+use serde::Serialize;
+use serde_json::Value;
+
+type NewAdapter = fn(GameId, Value) -> Result<Box<dyn GameAdapter>>;
+type LoadAdapter = fn(Value) -> Result<Box<dyn GameAdapter>>;
+
+/// A game that can be created, along with the factories for its adapter.
+#[derive(Serialize)]
+pub struct GameInfo {
+    pub game_type: &'static str,
+    // The number of players in a game with the default options
+    pub num_players: usize,
+    pub min_players: usize,
+    pub max_players: usize,
+    pub description: &'static str,
+    #[serde(skip)]
+    new_adapter: NewAdapter,
+    #[serde(skip)]
+    load_adapter: LoadAdapter,
+}
+
+fn new_adapter<A: GameAdapter + 'static>(
+    game_id: GameId,
+    options: Value,
+) -> Result<Box<dyn GameAdapter>> {
+    Ok(Box::new(A::new(game_id, options)?))
+}
+
+fn load_adapter<A: GameAdapter + 'static>(state: Value) -> Result<Box<dyn GameAdapter>> {
+    Ok(Box::new(A::load_state(state)?))
+}
+
+const fn game<A: GameAdapter + 'static>(
+    game_type: &'static str,
+    num_players: usize,
+    description: &'static str,
+) -> GameInfo {
+    GameInfo {
+        game_type,
+        num_players,
+        min_players: num_players,
+        max_players: num_players,
+        description,
+        new_adapter: new_adapter::<A>,
+        load_adapter: load_adapter::<A>,
+    }
+}
+
+impl GameInfo {
+    /// For games that can be played by a range of numbers of players, which is chosen with their
+    /// `players` option.
+    const fn with_players(self, min_players: usize, max_players: usize) -> Self {
+        GameInfo {
+            min_players,
+            max_players,
+            ..self
+        }
+    }
+}
+
+// Adding a game only requires an entry here, keyed by the `game_type` used in requests and payloads.
+// The API spec in static/openapi.yaml lists the game types separately in its GameType enum, which a
+// test keeps in step with this list.
+static GAMES: [GameInfo; 8] = [
+    game::<connect4::Connect4Adapter>(
+        "connect_4",
+        connect4::NUM_PLAYERS,
+        "Drop tokens into columns until one player connects a line of them",
+    ),
+    game::<snake::SnakeAdapter>(
+        "snake",
+        snake::DEFAULT_PLAYERS,
+        "Steer a growing snake around the board and outlast the other snakes",
+    )
+    .with_players(snake::MIN_PLAYERS, snake::MAX_PLAYERS),
+    game::<mnk::MnkAdapter>(
+        "mnk",
+        mnk::NUM_PLAYERS,
+        "Place marks on an m by n board until one player gets k in a row",
+    ),
+    game::<othello::OthelloAdapter>(
+        "othello",
+        othello::NUM_PLAYERS,
+        "Flip the opponent's discs by outflanking them and own the most discs at the end",
+    ),
+    game::<chess::ChessAdapter>(
+        "chess",
+        chess::NUM_PLAYERS,
+        "Standard chess with moves in UCI notation",
+    ),
+    game::<hearts::HeartsAdapter>(
+        "hearts",
+        hearts::NUM_PLAYERS,
+        "Avoid taking hearts and the queen of spades in this trick-taking card game",
+    ),
+    game::<iterated::IteratedAdapter>(
+        "iterated",
+        iterated::NUM_PLAYERS,
+        "Repeated rounds of Rock-Paper-Scissors or the Prisoner's Dilemma",
+    ),
+    game::<battleship::BattleshipAdapter>(
+        "battleship",
+        battleship::NUM_PLAYERS,
+        "Place ships in secret and sink all of the opponent's ships first",
+    ),
+];
+
+/// Lists every game that can be created.
+pub fn all() -> &'static [GameInfo] {
+    &GAMES
+}
+
+pub fn find(game_type: &str) -> Option<&'static GameInfo> {
+    GAMES.iter().find(|x| x.game_type == game_type)
+}
+
+impl GameInfo {
+    pub fn new_adapter(&self, game_id: GameId, options: Value) -> Result<Box<dyn GameAdapter>> {
+        (self.new_adapter)(game_id, options)
+    }
+
+    pub fn load_adapter(&self, state: Value) -> Result<Box<dyn GameAdapter>> {
+        (self.load_adapter)(state)
+    }
+
+    pub fn supports_players(&self, players: usize) -> bool {
+        (self.min_players..=self.max_players).contains(&players)
+    }
+
+    /// The default options for a game with the given number of players.
+    pub fn options_for_players(&self, players: usize) -> Value {
+        if self.min_players == self.max_players {
+            Value::Null
+        } else {
+            serde_json::json!({ "players": players })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::adapter::Stage;
+    use itertools::Itertools;
+
+    #[test]
+    fn game_types_are_unique() {
+        assert!(all().iter().map(|x| x.game_type).all_unique());
+    }
+
+    #[test]
+    fn api_spec_lists_every_game_type() {
+        let spec = include_str!("../../static/openapi.yaml");
+        let enum_values = spec
+            .lines()
+            .skip_while(|x| *x != "    GameType:")
+            .skip_while(|x| x.trim() != "- enum:")
+            .skip(1)
+            .map_while(|x| x.trim().strip_prefix("- "))
+            .collect_vec();
+
+        assert_eq!(enum_values, all().iter().map(|x| x.game_type).collect_vec());
+    }
+
+    #[test]
+    fn games_are_created_with_default_options_and_reloaded() {
+        for info in all() {
+            let adapter = info.new_adapter(GameId::new(), Value::Null).unwrap();
+            let loaded = info.load_adapter(adapter.save_state().unwrap()).unwrap();
+
+            assert_eq!(loaded.get_stage(), Stage::Waiting, "{}", info.game_type);
+            assert!(info.supports_players(info.num_players));
+        }
+    }
+
+    #[test]
+    fn games_start_with_every_supported_number_of_players() {
+        for info in all() {
+            for players in info.min_players..=info.max_players {
+                let options = info.options_for_players(players);
+                let mut adapter = info.new_adapter(GameId::new(), options).unwrap();
+                for i in 0..players {
+                    adapter.add_player(format!("Player {}", i + 1)).unwrap();
+                }
+
+                assert_eq!(adapter.get_stage(), Stage::InProgress, "{}", info.game_type);
+            }
+        }
+    }
+}
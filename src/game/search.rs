@@ -15,6 +15,8 @@ pub struct GameSummary {
     pub players: Vec<String>,
     pub stage: Stage,
     pub last_updated: DateTime<Utc>,
+    #[serde(skip)]
+    pub private: bool,
 }
 
 #[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
@@ -69,11 +71,12 @@ impl SearchEngine {
         let skip = (page - 1) * LIST_GAME_SUMMARY_COUNT;
 
         Ok(summaries
+            .filter(|s| !s.private)
             .sorted_by(|a, b| SearchEngine::compare_summaries(a, b, sort_key, sort_order))
             .skip(skip)
-            .filter(|s| game_type.map_or(true, |x| s.game_type == x))
-            .filter(|s| players.map_or(true, |x| s.players.len() == x))
-            .filter(|s| stage.map_or(true, |x| s.stage == x))
+            .filter(|s| game_type.is_none_or(|x| s.game_type == x))
+            .filter(|s| players.is_none_or(|x| s.players.len() == x))
+            .filter(|s| stage.is_none_or(|x| s.stage == x))
             .take(LIST_GAME_SUMMARY_COUNT)
             .collect())
     }
@@ -1,60 +1,57 @@
 use crate::game::adapter::{
     GameAdapter, GameAdapterError, GameAdapterErrorType, GenericGameMove, GenericGameState, Stage,
 };
-use crate::game::{GameId, GameType};
+use crate::game::GameId;
 use crate::notify::Notifier;
+use actix_web::error::{InternalError, JsonPayloadError};
+use actix_web::HttpResponse;
 use derive_more::Display;
-use rand::{thread_rng, Rng};
+use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::ops::Add;
 use std::vec;
 use std::vec::Vec;
 
-const NUM_PLAYERS: usize = 4;
+pub const DEFAULT_PLAYERS: usize = 4;
+pub const MIN_PLAYERS: usize = 2;
+pub const MAX_PLAYERS: usize = 8;
 
 const BOARD_MIN_X: i32 = -5;
 const BOARD_MAX_X: i32 = 5;
 const BOARD_MIN_Y: i32 = -5;
 const BOARD_MAX_Y: i32 = 5;
+const MAX_BOARD_SIZE: i32 = 50;
+const MIN_TICK_MS: u64 = 100;
+const MAX_TICK_MS: u64 = 60_000;
+const MIN_START_LENGTH: usize = 2;
+const GENERATED_START_LENGTH: usize = 3;
+const MAX_START_INSET: i32 = 2;
+const MAP_WALL: char = '#';
+const MAP_EMPTY: char = '.';
 
 const EMPTY_FRUIT_PROB: f64 = 0.5;
 const NON_EMPTY_FRUIT_PROB: f64 = 0.15;
+// Generated seeds are kept below 2^53 so that they can be represented exactly in JavaScript
+const MAX_GENERATED_SEED: u64 = 1 << 53;
 
-const STARTS: [[Point2D; 3]; NUM_PLAYERS] = [
-    [
-        Point2D::new(-3, -2),
-        Point2D::new(-3, -3),
-        Point2D::new(-2, -3),
-    ],
-    [
-        Point2D::new(-2, 3),
-        Point2D::new(-3, 3),
-        Point2D::new(-3, 2),
-    ],
-    [
-        Point2D::new(2, -3),
-        Point2D::new(3, -3),
-        Point2D::new(3, -2),
-    ],
-    [Point2D::new(3, 2), Point2D::new(3, 3), Point2D::new(2, 3)],
-];
-
+#[derive(Serialize, Deserialize)]
 pub struct SnakeAdapter {
     game_id: GameId,
     players: Vec<String>,
     stage: Stage,
+    #[serde(skip, default = "Notifier::new")]
     notifier: Notifier,
     game: Snake,
+    // Set when the game is ended early, instead of ranking the snakes
+    #[serde(default)]
+    winners: Option<Vec<String>>,
 }
 
-#[derive(Deserialize)]
-pub enum ConstSnake {
-    #[serde(rename = "snake")]
-    Snake,
-}
-
-#[derive(Deserialize, Debug, Copy, Clone, Eq, PartialEq, Display)]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq, Display)]
 #[serde(rename_all = "snake_case")]
 pub enum Direction {
     #[display(fmt = "up")]
@@ -67,23 +64,64 @@ pub enum Direction {
     Right,
 }
 
+impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+}
+
 #[derive(Deserialize)]
 struct SnakeRequestPayload {
-    game_type: ConstSnake,
     direction: Direction,
 }
 
-#[derive(Serialize, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SnakeLobbyAction {
+    Start,
+}
+
+#[derive(Deserialize)]
+struct SnakeLobbyPayload {
+    #[allow(dead_code)]
+    action: SnakeLobbyAction,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum StartTrigger {
+    // The game starts once the lobby is full
+    Full,
+    // The first player to join can also start the game before the lobby is full
+    Creator,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq, Hash)]
 struct Point2D {
     x: i32,
     y: i32,
 }
 
 impl Point2D {
-    fn random() -> Self {
+    fn random(rng: &mut impl Rng, min: &Point2D, max: &Point2D) -> Self {
+        Point2D {
+            x: rng.gen_range(min.x..=max.x),
+            y: rng.gen_range(min.y..=max.y),
+        }
+    }
+
+    fn is_within(&self, min: &Point2D, max: &Point2D) -> bool {
+        (min.x..=max.x).contains(&self.x) && (min.y..=max.y).contains(&self.y)
+    }
+
+    /// Moves a point that has left the world in one direction back in from the opposite side.
+    fn wrapped(&self, min: &Point2D, max: &Point2D) -> Self {
         Point2D {
-            x: thread_rng().gen_range(BOARD_MIN_X..=BOARD_MAX_X),
-            y: thread_rng().gen_range(BOARD_MIN_Y..=BOARD_MAX_Y),
+            x: min.x + (self.x - min.x).rem_euclid(max.x - min.x + 1),
+            y: min.y + (self.y - min.y).rem_euclid(max.y - min.y + 1),
         }
     }
 
@@ -92,7 +130,7 @@ impl Point2D {
     }
 }
 
-impl<'a, 'b> Add<&'a Direction> for &'b Point2D {
+impl<'a> Add<&'a Direction> for &Point2D {
     type Output = Point2D;
 
     fn add(self, rhs: &'a Direction) -> Self::Output {
@@ -117,47 +155,296 @@ impl<'a, 'b> Add<&'a Direction> for &'b Point2D {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum DeathCause {
+    Wall,
+    #[serde(rename = "self")]
+    OwnBody,
+    OtherSnake,
+    HeadOn,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+struct Death {
+    tick: usize,
+    cause: DeathCause,
+    // The length of the snake before the step in which it died
+    length: usize,
+}
+
 #[derive(Serialize)]
+struct Standing<'a> {
+    username: &'a str,
+    rank: usize,
+    length: usize,
+    alive: bool,
+}
+
+#[derive(Serialize, Deserialize)]
 struct SnakeResponsePayload {
     players: HashMap<String, VecDeque<Point2D>>,
     fruits: HashSet<Point2D>,
     world_min: Point2D,
     world_max: Point2D,
+    #[serde(default)]
+    tick: usize,
+    #[serde(default)]
+    deaths: HashMap<String, Death>,
+    #[serde(default)]
+    walls: HashSet<Point2D>,
+    #[serde(default)]
+    wraparound: bool,
+}
+
+impl SnakeResponsePayload {
+    /// The point reached by moving from a point in a direction, or `None` if that leaves the world.
+    fn step(&self, point: &Point2D, direction: &Direction) -> Option<Point2D> {
+        let next = point + direction;
+
+        if self.wraparound {
+            Some(next.wrapped(&self.world_min, &self.world_max))
+        } else if next.is_within(&self.world_min, &self.world_max) {
+            Some(next)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+struct SnakeOptions {
+    world_min: Point2D,
+    world_max: Point2D,
+    empty_fruit_probability: f64,
+    fruit_probability: f64,
+    players: usize,
+    // Advances the game at this interval using the latest direction of each snake, instead of
+    // waiting for every snake to move
+    tick_ms: Option<u64>,
+    // Games with the same seed and moves play out identically
+    seed: u64,
+    // Snakes that leave the world come back in on the opposite side
+    wraparound: bool,
+    // The rows of the world from top to bottom, where each cell is a wall or empty. The world is
+    // resized to fit the map, keeping world_min.
+    map: Vec<String>,
+    // The bodies of the snakes from head to tail, in the order that players join, or empty for
+    // starting positions generated for the number of players
+    starts: Vec<Vec<Point2D>>,
+    start: StartTrigger,
+}
+
+impl Default for SnakeOptions {
+    fn default() -> Self {
+        SnakeOptions {
+            world_min: Point2D::new(BOARD_MIN_X, BOARD_MIN_Y),
+            world_max: Point2D::new(BOARD_MAX_X, BOARD_MAX_Y),
+            empty_fruit_probability: EMPTY_FRUIT_PROB,
+            fruit_probability: NON_EMPTY_FRUIT_PROB,
+            players: DEFAULT_PLAYERS,
+            tick_ms: None,
+            seed: thread_rng().gen_range(0..MAX_GENERATED_SEED),
+            wraparound: false,
+            map: vec![],
+            starts: vec![],
+            start: StartTrigger::Full,
+        }
+    }
+}
+
+impl SnakeOptions {
+    fn validate(&self) -> Result<(), String> {
+        let (min, max) = (&self.world_min, &self.world_max);
+
+        if min.x > max.x || min.y > max.y {
+            Err(String::from(
+                "world_min must not be above or right of world_max",
+            ))
+        } else if max.x - min.x >= MAX_BOARD_SIZE || max.y - min.y >= MAX_BOARD_SIZE {
+            Err(format!(
+                "the world must be at most {} cells wide and high",
+                MAX_BOARD_SIZE
+            ))
+        } else if ![self.empty_fruit_probability, self.fruit_probability]
+            .iter()
+            .all(|x| (0.0..=1.0).contains(x))
+        {
+            Err(String::from("fruit probabilities must be between 0 and 1"))
+        } else if !(MIN_PLAYERS..=MAX_PLAYERS).contains(&self.players) {
+            Err(format!(
+                "players must be between {} and {}",
+                MIN_PLAYERS, MAX_PLAYERS
+            ))
+        } else if self.map.iter().any(|x| {
+            x.is_empty()
+                || x.chars().count() != self.map[0].chars().count()
+                || x.chars().any(|c| c != MAP_WALL && c != MAP_EMPTY)
+        }) {
+            Err(format!(
+                "map rows must be the same length and only contain '{}' and '{}'",
+                MAP_WALL, MAP_EMPTY
+            ))
+        } else if !self.starts.is_empty() && self.starts.len() < self.players {
+            Err(String::from("there must be a start for every player"))
+        } else if self
+            .tick_ms
+            .is_some_and(|x| !(MIN_TICK_MS..=MAX_TICK_MS).contains(&x))
+        {
+            Err(format!(
+                "tick_ms must be between {} and {}",
+                MIN_TICK_MS, MAX_TICK_MS
+            ))
+        } else {
+            // Check the starting positions for every number of players the game can start with
+            let min_players = match self.start {
+                StartTrigger::Full => self.players,
+                StartTrigger::Creator => MIN_PLAYERS,
+            };
+            (min_players..=self.players).try_for_each(|x| self.validate_starts(x))
+        }
+    }
+
+    fn validate_starts(&self, players: usize) -> Result<(), String> {
+        let (min, max) = (&self.world_min, &self.world_max);
+        let walls = self.walls();
+        let starts = self
+            .starts_for(players)
+            .ok_or_else(|| format!("the world is too small for {} players", players))?;
+
+        if !starts.iter().flatten().all(|x| x.is_within(min, max)) {
+            Err(String::from(
+                "the world is too small for the starting positions",
+            ))
+        } else if starts.iter().flatten().any(|x| walls.contains(x)) {
+            Err(String::from("starting positions must not be on a wall"))
+        } else if !starts.iter().flatten().all_unique() {
+            Err(String::from("starting positions must not overlap"))
+        } else if starts.iter().any(|x| {
+            x.len() < MIN_START_LENGTH
+                || x.iter().tuple_windows().any(|(a, b)| !self.adjacent(a, b))
+        }) {
+            Err(format!(
+                "starting positions must be at least {} cells that are next to each other",
+                MIN_START_LENGTH
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The bodies of the snakes when the game starts with the given number of players.
+    fn starts_for(&self, players: usize) -> Option<Vec<Vec<Point2D>>> {
+        if self.starts.is_empty() {
+            generate_starts(&self.world_min, &self.world_max, players)
+        } else {
+            Some(self.starts[..players].to_vec())
+        }
+    }
+
+    /// Resizes the world to fit the map, if there is one.
+    fn fit_map(&mut self) {
+        if let Some(row) = self.map.first() {
+            self.world_max = Point2D::new(
+                self.world_min
+                    .x
+                    .saturating_add(row.chars().count() as i32 - 1),
+                self.world_min.y.saturating_add(self.map.len() as i32 - 1),
+            );
+        }
+    }
+
+    /// The walls on the map, whose bottom row is at the bottom of the world.
+    fn walls(&self) -> HashSet<Point2D> {
+        let min = self.world_min;
+
+        self.map
+            .iter()
+            .rev()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.chars()
+                    .enumerate()
+                    .filter(|&(_, c)| c == MAP_WALL)
+                    .map(move |(x, _)| Point2D::new(min.x + x as i32, min.y + y as i32))
+            })
+            .collect()
+    }
+
+    fn adjacent(&self, a: &Point2D, b: &Point2D) -> bool {
+        Direction::ALL.iter().any(|x| {
+            let next = a + x;
+            next == *b || (self.wraparound && next.wrapped(&self.world_min, &self.world_max) == *b)
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct SnakeStatePayload<'a> {
+    #[serde(flatten)]
+    state: &'a SnakeResponsePayload,
+    ranking: &'a [Standing<'a>],
+    options: &'a SnakeOptions,
 }
 
+#[derive(Serialize, Deserialize)]
 struct Snake {
-    game_id: GameId,
     moves: HashMap<String, Direction>,
     state: SnakeResponsePayload,
+    #[serde(default)]
+    options: SnakeOptions,
 }
 
 impl GameAdapter for SnakeAdapter {
-    fn new(game_id: GameId) -> Self
+    fn new(game_id: GameId, options: Value) -> actix_web::Result<Self>
     where
         Self: Sized,
     {
-        SnakeAdapter {
+        let options = serde_json::from_value::<Option<SnakeOptions>>(options)
+            .map_err(|e| e.to_string())
+            .and_then(|options| {
+                let mut options = options.unwrap_or_default();
+                options.fit_map();
+                options.validate()?;
+                Ok(options)
+            })
+            .map_err(|e| {
+                GameAdapterError::actix_err(game_id, GameAdapterErrorType::InvalidOptions(e))
+            })?;
+
+        Ok(SnakeAdapter {
             game_id,
             players: vec![],
             stage: Stage::Waiting,
             notifier: Notifier::new(),
             game: Snake {
-                game_id,
                 moves: HashMap::new(),
                 state: SnakeResponsePayload {
                     players: HashMap::new(),
                     fruits: HashSet::new(),
-                    world_min: Point2D {
-                        x: BOARD_MIN_X,
-                        y: BOARD_MIN_Y,
-                    },
-                    world_max: Point2D {
-                        x: BOARD_MAX_X,
-                        y: BOARD_MAX_Y,
-                    },
+                    world_min: options.world_min,
+                    world_max: options.world_max,
+                    tick: 0,
+                    deaths: HashMap::new(),
+                    walls: options.walls(),
+                    wraparound: options.wraparound,
                 },
+                options,
             },
-        }
+            winners: None,
+        })
+    }
+
+    fn load_state(state: Value) -> actix_web::Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(serde_json::from_value(state)?)
+    }
+
+    fn save_state(&self) -> actix_web::Result<Value> {
+        Ok(serde_json::to_value(self)?)
     }
 
     fn get_notifier(&self) -> &Notifier {
@@ -165,14 +452,12 @@ impl GameAdapter for SnakeAdapter {
     }
 
     fn add_player(&mut self, username: String) -> actix_web::Result<()> {
-        assert!(self.players.len() < NUM_PLAYERS);
+        assert!(self.players.len() < self.game.options.players);
         assert_eq!(self.stage, Stage::Waiting);
 
-        let start = VecDeque::from(STARTS[self.players.len()]);
-        self.players.push(username.clone());
-        self.game.state.players.insert(username, start);
-        if self.players.len() == NUM_PLAYERS {
-            self.stage = Stage::InProgress;
+        self.players.push(username);
+        if self.players.len() == self.game.options.players {
+            self.start();
         }
 
         self.notifier.send();
@@ -184,6 +469,10 @@ impl GameAdapter for SnakeAdapter {
     }
 
     fn play_move(&mut self, game_move: GenericGameMove) -> actix_web::Result<()> {
+        if self.stage == Stage::Waiting && self.game.options.start == StartTrigger::Creator {
+            return self.start_early(game_move);
+        }
+
         if self.stage == Stage::Waiting || self.stage == Stage::Ended {
             return Err(GameAdapterError::actix_err(
                 self.game_id,
@@ -194,7 +483,10 @@ impl GameAdapter for SnakeAdapter {
         let request_payload = serde_json::from_value::<SnakeRequestPayload>(game_move.payload)?;
         let user = game_move.player;
 
-        if !self.game.state.players.contains_key(&user) || self.game.moves.contains_key(&user) {
+        let real_time = self.game.options.tick_ms.is_some();
+        if !self.game.state.players.contains_key(&user)
+            || (!real_time && self.game.moves.contains_key(&user))
+        {
             return Err(GameAdapterError::actix_err(
                 self.game_id,
                 GameAdapterErrorType::InvalidPlayer(user),
@@ -202,6 +494,29 @@ impl GameAdapter for SnakeAdapter {
         }
 
         self.game.record_move(user, request_payload.direction)?;
+        // Directions in real-time games take effect at the next tick, which lets players know
+        if real_time {
+            return Ok(());
+        }
+
+        if self.game.state.players.len() <= 1 {
+            self.stage = Stage::Ended;
+        }
+
+        self.notifier.send();
+
+        Ok(())
+    }
+
+    fn handle_timeout(&mut self) -> actix_web::Result<()> {
+        if self.stage != Stage::InProgress {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidGameStage(self.stage),
+            ));
+        }
+
+        self.game.repeat_missing_moves()?;
 
         if self.game.state.players.len() <= 1 {
             self.stage = Stage::Ended;
@@ -212,60 +527,190 @@ impl GameAdapter for SnakeAdapter {
         Ok(())
     }
 
+    fn end_game(&mut self, winners: Vec<String>) {
+        self.winners = Some(winners);
+        self.stage = Stage::Ended;
+    }
+
     fn get_stage(&self) -> Stage {
         self.stage
     }
 
     fn get_encoded_state(&self) -> actix_web::Result<GenericGameState> {
+        // In real-time mode snakes can change direction any number of times before each tick
         let all_players = self.game.state.players.keys();
+        let real_time = self.game.options.tick_ms.is_some();
+        let in_progress = self.stage == Stage::InProgress;
         let can_move = all_players
-            .filter(|&x| !self.game.moves.contains_key(x))
+            .filter(|&x| in_progress && (real_time || !self.game.moves.contains_key(x)))
             .cloned()
             .collect();
 
+        let ranking = self.game.ranking();
+
         Ok(GenericGameState {
             players: self.players.clone(),
             stage: self.stage,
             can_move,
-            winners: if self.stage == Stage::Ended {
-                self.game.state.players.keys().cloned().collect()
+            winners: if let Some(winners) = &self.winners {
+                winners.clone()
+            } else if self.stage == Stage::Ended {
+                ranking
+                    .iter()
+                    .filter(|x| x.rank == 1)
+                    .map(|x| x.username.to_string())
+                    .collect()
             } else {
                 vec![]
             },
-            payload: serde_json::to_value(&self.game.state)?,
+            deadline: None,
+            termination: None,
+            draw_offers: vec![],
+            payload: serde_json::to_value(SnakeStatePayload {
+                state: &self.game.state,
+                ranking: &ranking,
+                options: &self.game.options,
+            })?,
         })
     }
 
-    fn get_type(&self) -> GameType {
-        GameType::Snake
+    fn get_contenders(&self) -> actix_web::Result<Vec<String>> {
+        Ok(self
+            .players
+            .iter()
+            .filter(|x| self.game.state.players.contains_key(*x))
+            .cloned()
+            .collect())
+    }
+
+    fn get_tick_interval_ms(&self) -> Option<u64> {
+        self.game.options.tick_ms
     }
 }
 
+impl SnakeAdapter {
+    /// Places the snake of every player that has joined and starts the game.
+    fn start(&mut self) {
+        let starts = self.game.options.starts_for(self.players.len()).unwrap();
+
+        for (player, start) in self.players.iter().zip(starts) {
+            self.game
+                .state
+                .players
+                .insert(player.clone(), VecDeque::from(start));
+        }
+        self.stage = Stage::InProgress;
+    }
+
+    /// Lets the first player to join start the game before the lobby is full.
+    fn start_early(&mut self, game_move: GenericGameMove) -> actix_web::Result<()> {
+        serde_json::from_value::<SnakeLobbyPayload>(game_move.payload).map_err(|e| {
+            InternalError::from_response(
+                "",
+                HttpResponse::BadRequest()
+                    .content_type("text/plain")
+                    .body(JsonPayloadError::Deserialize(e).to_string()),
+            )
+        })?;
+
+        if self.players.first() != Some(&game_move.player) {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidPlayer(game_move.player),
+            ));
+        }
+
+        if self.players.len() < MIN_PLAYERS {
+            return Err(GameAdapterError::actix_err(
+                self.game_id,
+                GameAdapterErrorType::InvalidMove(format!(
+                    "at least {} players are needed to start",
+                    MIN_PLAYERS
+                )),
+            ));
+        }
+
+        self.start();
+        self.notifier.send();
+
+        Ok(())
+    }
+}
+
+/// Spaces snakes evenly around a ring inside the world, all heading clockwise along it, so that
+/// every snake is as far from the walls and from the next snake as the others. Rings further from
+/// the walls are preferred, as long as there is room between each snake and the next.
+fn generate_starts(min: &Point2D, max: &Point2D, players: usize) -> Option<Vec<Vec<Point2D>>> {
+    (0..=MAX_START_INSET).rev().find_map(|inset| {
+        let (left, bottom) = (min.x + inset, min.y + inset);
+        let (right, top) = (max.x - inset, max.y - inset);
+        if right <= left || top <= bottom {
+            return None;
+        }
+
+        let ring = (left..right)
+            .map(|x| Point2D::new(x, top))
+            .chain((bottom + 1..=top).rev().map(|y| Point2D::new(right, y)))
+            .chain((left + 1..=right).rev().map(|x| Point2D::new(x, bottom)))
+            .chain((bottom..top).map(|y| Point2D::new(left, y)))
+            .collect::<Vec<_>>();
+        let spacing = ring.len() / players;
+        if spacing <= GENERATED_START_LENGTH {
+            return None;
+        }
+
+        let starts = (0..players)
+            .map(|i| {
+                (0..GENERATED_START_LENGTH)
+                    .rev()
+                    .map(|j| ring[i * spacing + j])
+                    .collect()
+            })
+            .collect();
+        Some(starts)
+    })
+}
+
 impl Snake {
     fn time_step(&mut self) -> actix_web::Result<()> {
-        let mut occupied: HashSet<Point2D> = HashSet::new();
+        self.state.tick += 1;
+        let mut occupied: HashMap<Point2D, &String> = HashMap::new();
+        let mut lengths: HashMap<&String, usize> = HashMap::new();
 
         for (player, _) in self.moves.iter() {
             let deque = self.state.players.get(player).unwrap();
-            occupied.extend(deque.iter());
+            occupied.extend(deque.iter().map(|&x| (x, player)));
+            lengths.insert(player, deque.len());
         }
 
+        let mut deaths = vec![];
         let mut newly_occupied: HashMap<Point2D, &String> = HashMap::new();
         for (player, dir) in self.moves.iter() {
+            let new_point = self.state.step(&self.state.players[player][0], dir);
             let deque = self.state.players.get_mut(player).unwrap();
-            let new_point = deque.front().unwrap() + dir;
-
-            if new_point.y > BOARD_MAX_Y
-                || new_point.y < BOARD_MIN_Y
-                || new_point.x > BOARD_MAX_X
-                || new_point.x < BOARD_MIN_X
-                || occupied.contains(&new_point)
-            {
+
+            let Some(new_point) = new_point.filter(|x| !self.state.walls.contains(x)) else {
+                self.state.players.remove(player);
+                deaths.push((player, DeathCause::Wall));
+                continue;
+            };
+
+            if let Some(&owner) = occupied.get(&new_point) {
                 self.state.players.remove(player);
+                deaths.push((
+                    player,
+                    if owner == player {
+                        DeathCause::OwnBody
+                    } else {
+                        DeathCause::OtherSnake
+                    },
+                ));
             } else {
                 if let Some(collided) = newly_occupied.insert(new_point, player) {
                     self.state.players.remove(collided);
                     self.state.players.remove(player);
+                    deaths.push((collided, DeathCause::HeadOn));
+                    deaths.push((player, DeathCause::HeadOn));
                 } else {
                     deque.push_front(new_point);
                     if self.state.fruits.contains(&new_point) {
@@ -276,30 +721,127 @@ impl Snake {
                 }
             }
         }
-        occupied.extend(newly_occupied.keys());
-        self.moves.clear();
+        for (player, cause) in deaths {
+            let death = Death {
+                tick: self.state.tick,
+                cause,
+                length: lengths[player],
+            };
+            self.state.deaths.insert(player.clone(), death);
+        }
+        occupied.extend(newly_occupied);
 
         let fruit_prob = if self.state.fruits.is_empty() {
-            EMPTY_FRUIT_PROB
+            self.options.empty_fruit_probability
         } else {
-            NON_EMPTY_FRUIT_PROB
+            self.options.fruit_probability
         };
 
-        if rand::thread_rng().gen_bool(fruit_prob) {
+        let mut rng = self.step_rng();
+        if rng.gen_bool(fruit_prob) {
             // Attempt to spawn a single new fruit
             for _ in 0..10 {
-                let fruit_pos = Point2D::random();
-                if !occupied.contains(&fruit_pos) && !self.state.fruits.contains(&fruit_pos) {
+                let fruit_pos =
+                    Point2D::random(&mut rng, &self.state.world_min, &self.state.world_max);
+                if !occupied.contains_key(&fruit_pos)
+                    && !self.state.fruits.contains(&fruit_pos)
+                    && !self.state.walls.contains(&fruit_pos)
+                {
                     self.state.fruits.insert(fruit_pos);
                     break;
                 }
             }
         }
+        self.moves.clear();
 
         Ok(())
     }
 
+    /// A generator for the current step, which is derived from the seed and the step so that the
+    /// state does not need to hold one.
+    fn step_rng(&self) -> StdRng {
+        let mut seed = <StdRng as SeedableRng>::Seed::default();
+        seed[..8].copy_from_slice(&self.options.seed.to_le_bytes());
+        seed[8..16].copy_from_slice(&(self.state.tick as u64).to_le_bytes());
+        StdRng::from_seed(seed)
+    }
+
+    /// Ranks every player, with snakes that are still alive first, then snakes that died later,
+    /// then longer snakes. Players that cannot be told apart share a rank.
+    fn ranking(&self) -> Vec<Standing<'_>> {
+        let alive = self.state.players.iter().map(|(username, body)| {
+            let standing = Standing {
+                username,
+                rank: 0,
+                length: body.len(),
+                alive: true,
+            };
+            (usize::MAX, standing)
+        });
+        let dead = self.state.deaths.iter().map(|(username, death)| {
+            let standing = Standing {
+                username,
+                rank: 0,
+                length: death.length,
+                alive: false,
+            };
+            (death.tick, standing)
+        });
+
+        let mut ranking = alive.chain(dead).collect::<Vec<_>>();
+        ranking.sort_by(|(a_tick, a), (b_tick, b)| {
+            (b_tick, b.length, a.username).cmp(&(a_tick, a.length, b.username))
+        });
+
+        let keys = ranking
+            .iter()
+            .map(|(tick, x)| (*tick, x.length))
+            .collect::<Vec<_>>();
+        ranking
+            .into_iter()
+            .enumerate()
+            .map(|(i, (_, mut standing))| {
+                standing.rank = keys.iter().position(|&x| x == keys[i]).unwrap() + 1;
+                standing
+            })
+            .collect()
+    }
+
+    /// The direction in which a snake last moved, from the position of its head.
+    fn heading(&self, player: &str) -> Direction {
+        let body = &self.state.players[player];
+
+        Direction::ALL
+            .into_iter()
+            .find(|x| body.len() > 1 && self.state.step(&body[1], x) == Some(body[0]))
+            .unwrap_or(Direction::Right)
+    }
+
+    /// Moves every snake that has not moved yet in the direction it is heading.
+    fn repeat_missing_moves(&mut self) -> actix_web::Result<()> {
+        let missing = self
+            .state
+            .players
+            .keys()
+            .filter(|&x| !self.moves.contains_key(x))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for player in missing {
+            let direction = self.heading(&player);
+            self.moves.insert(player, direction);
+        }
+
+        self.time_step()
+    }
+
     fn record_move(&mut self, player: String, direction: Direction) -> actix_web::Result<()> {
+        if self.options.tick_ms.is_some() {
+            // The latest direction is used at the next tick
+            self.moves.insert(player, direction);
+            return Ok(());
+        }
+
         assert_eq!(self.moves.insert(player, direction), None);
 
         if self.moves.len() == self.state.players.len() {
@@ -309,3 +851,287 @@ impl Snake {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_adapter(options: Value) -> SnakeAdapter {
+        let mut adapter = SnakeAdapter::new(GameId::new(), options).unwrap();
+        for i in 0..adapter.game.options.players {
+            adapter.add_player(format!("Player {}", i + 1)).unwrap();
+        }
+        adapter
+    }
+
+    fn play(adapter: &mut SnakeAdapter, player: usize, direction: &str) -> actix_web::Result<()> {
+        adapter.play_move(GenericGameMove {
+            player: format!("Player {}", player + 1),
+            payload: serde_json::json!({ "game_type": "snake", "direction": direction }),
+        })
+    }
+
+    fn head(adapter: &SnakeAdapter, player: usize) -> Point2D {
+        adapter.game.state.players[&format!("Player {}", player + 1)][0]
+    }
+
+    #[test]
+    fn snakes_move_once_everyone_has_moved() {
+        let mut adapter = create_adapter(Value::Null);
+
+        for (player, direction) in ["up", "down", "left"].into_iter().enumerate() {
+            play(&mut adapter, player, direction).unwrap();
+        }
+        assert!(play(&mut adapter, 0, "right").is_err());
+        assert_eq!(head(&adapter, 0), Point2D::new(-1, 3));
+
+        play(&mut adapter, 3, "up").unwrap();
+        assert_eq!(head(&adapter, 0), Point2D::new(-1, 4));
+        assert_eq!(adapter.game.state.players.len(), DEFAULT_PLAYERS);
+        assert_eq!(adapter.get_tick_interval_ms(), None);
+    }
+
+    #[test]
+    fn real_time_snakes_move_on_each_tick() {
+        let mut adapter = create_adapter(serde_json::json!({ "tick_ms": 200 }));
+
+        play(&mut adapter, 0, "down").unwrap();
+        play(&mut adapter, 0, "up").unwrap();
+        assert_eq!(head(&adapter, 0), Point2D::new(-1, 3));
+        assert_eq!(
+            adapter.get_encoded_state().unwrap().can_move.len(),
+            DEFAULT_PLAYERS
+        );
+
+        adapter.handle_timeout().unwrap();
+        assert_eq!(head(&adapter, 0), Point2D::new(-1, 4));
+
+        // Snakes that do not move keep their heading
+        adapter.handle_timeout().unwrap();
+        assert_eq!(head(&adapter, 0), Point2D::new(-1, 5));
+        assert_eq!(head(&adapter, 1), Point2D::new(3, -1));
+        assert_eq!(adapter.get_tick_interval_ms(), Some(200));
+    }
+
+    fn place(adapter: &mut SnakeAdapter, player: usize, body: &[(i32, i32)]) {
+        let body = body.iter().map(|&(x, y)| Point2D::new(x, y)).collect();
+        adapter
+            .game
+            .state
+            .players
+            .insert(format!("Player {}", player + 1), body);
+    }
+
+    #[test]
+    fn deaths_are_recorded_and_ranked() {
+        let mut adapter = create_adapter(serde_json::json!({ "players": 2 }));
+        place(&mut adapter, 0, &[(5, 0), (4, 0), (3, 0)]);
+        place(&mut adapter, 1, &[(0, 0), (0, 1)]);
+
+        play(&mut adapter, 0, "right").unwrap();
+        play(&mut adapter, 1, "down").unwrap();
+
+        let state = adapter.get_encoded_state().unwrap();
+        let death = adapter.game.state.deaths["Player 1"];
+        assert_eq!(state.stage, Stage::Ended);
+        assert_eq!(state.winners, vec!["Player 2"]);
+        assert_eq!(
+            (death.tick, death.cause, death.length),
+            (1, DeathCause::Wall, 3)
+        );
+        assert_eq!(state.payload["ranking"][1]["rank"], 2);
+    }
+
+    #[test]
+    fn dead_snakes_are_not_contenders() {
+        let mut adapter = create_adapter(serde_json::json!({ "players": 3 }));
+        place(&mut adapter, 0, &[(5, 0), (4, 0)]);
+        place(&mut adapter, 1, &[(1, 2), (0, 2)]);
+        place(&mut adapter, 2, &[(1, 4), (0, 4)]);
+
+        for player in 0..3 {
+            play(&mut adapter, player, "right").unwrap();
+        }
+
+        assert_eq!(adapter.get_stage(), Stage::InProgress);
+        assert_eq!(
+            adapter.get_contenders().unwrap(),
+            vec!["Player 2", "Player 3"]
+        );
+    }
+
+    #[test]
+    fn longest_snake_wins_when_all_die_at_once() {
+        let mut adapter = create_adapter(serde_json::json!({ "players": 2 }));
+        place(&mut adapter, 0, &[(0, 0), (-1, 0)]);
+        place(&mut adapter, 1, &[(2, 0), (3, 0), (4, 0)]);
+
+        play(&mut adapter, 0, "right").unwrap();
+        play(&mut adapter, 1, "left").unwrap();
+
+        let state = adapter.get_encoded_state().unwrap();
+        assert_eq!(state.winners, vec!["Player 2"]);
+        assert!(adapter
+            .game
+            .state
+            .deaths
+            .values()
+            .all(|x| x.cause == DeathCause::HeadOn));
+    }
+
+    #[test]
+    fn games_with_the_same_seed_are_identical() {
+        let options = serde_json::json!({
+            "players": 2,
+            "seed": 42,
+            "empty_fruit_probability": 1.0,
+            "fruit_probability": 1.0,
+        });
+        let mut adapters = [create_adapter(options.clone()), create_adapter(options)];
+
+        for adapter in adapters.iter_mut() {
+            for _ in 0..3 {
+                play(adapter, 0, "right").unwrap();
+                play(adapter, 1, "left").unwrap();
+            }
+        }
+
+        let [a, b] = adapters.map(|x| x.game.state.fruits);
+        assert!(!a.is_empty());
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn snakes_wrap_around_the_world() {
+        let mut adapter = create_adapter(serde_json::json!({
+            "players": 2,
+            "wraparound": true,
+            "starts": [
+                [{ "x": 5, "y": 0 }, { "x": 4, "y": 0 }],
+                [{ "x": 0, "y": 3 }, { "x": 0, "y": 4 }],
+            ],
+        }));
+
+        play(&mut adapter, 0, "right").unwrap();
+        play(&mut adapter, 1, "down").unwrap();
+        assert_eq!(head(&adapter, 0), Point2D::new(-5, 0));
+
+        // The heading is still right after wrapping around
+        adapter.handle_timeout().unwrap();
+        assert_eq!(head(&adapter, 0), Point2D::new(-4, 0));
+        assert_eq!(head(&adapter, 1), Point2D::new(0, 1));
+    }
+
+    #[test]
+    fn snakes_die_on_walls_from_the_map() {
+        let mut adapter = create_adapter(serde_json::json!({
+            "players": 2,
+            "map": [".....", ".#...", "....."],
+            "starts": [
+                [{ "x": -5, "y": -4 }, { "x": -5, "y": -5 }],
+                [{ "x": -2, "y": -3 }, { "x": -1, "y": -3 }],
+            ],
+        }));
+        assert_eq!(adapter.game.state.world_max, Point2D::new(-1, -3));
+
+        play(&mut adapter, 0, "right").unwrap();
+        play(&mut adapter, 1, "down").unwrap();
+
+        let state = adapter.get_encoded_state().unwrap();
+        assert_eq!(state.winners, vec!["Player 2"]);
+        assert_eq!(
+            adapter.game.state.deaths["Player 1"].cause,
+            DeathCause::Wall
+        );
+        assert_eq!(
+            state.payload["walls"],
+            serde_json::json!([{ "x": -4, "y": -4 }])
+        );
+    }
+
+    #[test]
+    fn starting_positions_are_validated() {
+        let error = |starts| {
+            let options =
+                serde_json::json!({ "players": 2, "map": ["...", "#..", "..."], "starts": starts });
+            SnakeAdapter::new(GameId::new(), options)
+                .err()
+                .map(|x| x.to_string())
+        };
+        let first = serde_json::json!([{ "x": -5, "y": -3 }, { "x": -4, "y": -3 }]);
+        let on_wall = serde_json::json!([{ "x": -5, "y": -4 }, { "x": -5, "y": -5 }]);
+        let apart = serde_json::json!([{ "x": -3, "y": -3 }, { "x": -3, "y": -5 }]);
+        let second = serde_json::json!([{ "x": -3, "y": -3 }, { "x": -3, "y": -4 }]);
+
+        assert!(error(serde_json::json!([first]))
+            .unwrap()
+            .contains("every player"));
+        assert!(error(serde_json::json!([first, on_wall]))
+            .unwrap()
+            .contains("wall"));
+        assert!(error(serde_json::json!([first, apart]))
+            .unwrap()
+            .contains("next to"));
+        assert!(error(serde_json::json!([first, first]))
+            .unwrap()
+            .contains("overlap"));
+        assert_eq!(error(serde_json::json!([first, second])), None);
+    }
+
+    #[test]
+    fn starts_are_generated_for_every_player_count() {
+        let (min, max) = (Point2D::new(-5, -5), Point2D::new(5, 5));
+
+        for players in MIN_PLAYERS..=MAX_PLAYERS {
+            let options = serde_json::json!({ "players": players });
+            let adapter = create_adapter(options);
+            assert_eq!(adapter.game.state.players.len(), players);
+        }
+
+        // Four snakes are the same when the world is turned by a quarter
+        let starts = generate_starts(&min, &max, 4).unwrap();
+        for (i, start) in starts.iter().enumerate() {
+            let turned = start.iter().map(|p| Point2D::new(p.y, -p.x)).collect_vec();
+            assert_eq!(turned, starts[(i + 1) % 4]);
+        }
+        assert_eq!(generate_starts(&min, &max, 30), None);
+    }
+
+    #[test]
+    fn creator_can_start_before_the_lobby_is_full() {
+        let options = serde_json::json!({ "players": 8, "start": "creator" });
+        let mut adapter = SnakeAdapter::new(GameId::new(), options).unwrap();
+        let start = |adapter: &mut SnakeAdapter, player: &str| {
+            adapter.play_move(GenericGameMove {
+                player: player.to_string(),
+                payload: serde_json::json!({ "game_type": "snake", "action": "start" }),
+            })
+        };
+
+        adapter.add_player(String::from("Player 1")).unwrap();
+        assert!(start(&mut adapter, "Player 1").is_err());
+        adapter.add_player(String::from("Player 2")).unwrap();
+        assert!(start(&mut adapter, "Player 2").is_err());
+
+        start(&mut adapter, "Player 1").unwrap();
+        assert_eq!(adapter.get_stage(), Stage::InProgress);
+        assert_eq!(adapter.game.state.players.len(), 2);
+    }
+
+    #[test]
+    fn tick_interval_is_validated() {
+        assert!(SnakeAdapter::new(GameId::new(), serde_json::json!({ "tick_ms": 10 })).is_err());
+    }
+
+    #[test]
+    fn ending_early_overrides_ranking() {
+        let mut adapter = create_adapter(Value::Null);
+
+        adapter.end_game(vec![String::from("Player 2"), String::from("Player 3")]);
+
+        let state = adapter.get_encoded_state().unwrap();
+        assert_eq!(state.stage, Stage::Ended);
+        assert_eq!(state.winners, vec!["Player 2", "Player 3"]);
+        assert!(state.can_move.is_empty());
+    }
+}
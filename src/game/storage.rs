@@ -0,0 +1,402 @@
+use crate::game::adapter::Termination;
+use crate::game::history::{History, HistoryEntry};
+use crate::game::rating::StoredRatings;
+use crate::game::tournament::Tournament;
+use crate::game::{GameId, GameSettings, GameType, Session, SessionId, TournamentId};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+const FILE_EXTENSION: &str = "json";
+// Histories are appended to as JSON lines instead of being rewritten with the rest of the game
+const HISTORY_EXTENSION: &str = "jsonl";
+const GAMES_DIRECTORY: &str = "games";
+const TOURNAMENTS_DIRECTORY: &str = "tournaments";
+const RATINGS_FILE: &str = "ratings.json";
+
+#[derive(Serialize, Deserialize)]
+pub struct StoredGame {
+    pub game_id: GameId,
+    pub game_type: GameType,
+    pub adapter: Value,
+    pub sessions: HashMap<SessionId, Session>,
+    pub last_update: DateTime<Utc>,
+    // Read from the history file, or from the game file if it was saved with its history
+    #[serde(default, skip_serializing)]
+    pub history: History,
+    #[serde(default)]
+    pub rated: bool,
+    // The clock of the game's notifier, which is not part of the adapter state
+    #[serde(default)]
+    pub clock: usize,
+    #[serde(default)]
+    pub settings: GameSettings,
+    #[serde(default)]
+    pub deadline: Option<DateTime<Utc>>,
+    // The players the deadline is for, so that it is not renewed for them after a restart
+    #[serde(default)]
+    pub turn: Vec<String>,
+    #[serde(default)]
+    pub termination: Option<Termination>,
+    #[serde(default)]
+    pub draw_offers: Vec<String>,
+}
+
+pub trait Storage: Send + Sync {
+    fn save_game(&self, game: &StoredGame) -> io::Result<()>;
+    fn append_history(&self, game_id: GameId, entries: &[HistoryEntry]) -> io::Result<()>;
+    fn remove_game(&self, game_id: GameId) -> io::Result<()>;
+    fn load_games(&self) -> io::Result<Vec<StoredGame>>;
+    fn save_tournament(&self, tournament: &Tournament) -> io::Result<()>;
+    fn remove_tournament(&self, tournament_id: TournamentId) -> io::Result<()>;
+    fn load_tournaments(&self) -> io::Result<Vec<Tournament>>;
+    fn save_ratings(&self, ratings: &StoredRatings) -> io::Result<()>;
+    fn load_ratings(&self) -> io::Result<StoredRatings>;
+}
+
+/// Keeps nothing, so games only live as long as the server process.
+pub struct MemoryStorage;
+
+impl Storage for MemoryStorage {
+    fn save_game(&self, _: &StoredGame) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn append_history(&self, _: GameId, _: &[HistoryEntry]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn remove_game(&self, _: GameId) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn load_games(&self) -> io::Result<Vec<StoredGame>> {
+        Ok(vec![])
+    }
+
+    fn save_tournament(&self, _: &Tournament) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn remove_tournament(&self, _: TournamentId) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn load_tournaments(&self) -> io::Result<Vec<Tournament>> {
+        Ok(vec![])
+    }
+
+    fn save_ratings(&self, _: &StoredRatings) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn load_ratings(&self) -> io::Result<StoredRatings> {
+        Ok(StoredRatings::new())
+    }
+}
+
+/// Stores each game as a JSON file in the `games` subdirectory of a directory, next to a file of
+/// its history, and each tournament in the `tournaments` subdirectory, alongside a JSON file of
+/// ratings.
+pub struct FileStorage {
+    directory: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(directory: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(directory.join(GAMES_DIRECTORY))?;
+        fs::create_dir_all(directory.join(TOURNAMENTS_DIRECTORY))?;
+        Ok(FileStorage { directory })
+    }
+
+    fn game_path(&self, game_id: GameId, extension: &str) -> PathBuf {
+        self.directory
+            .join(GAMES_DIRECTORY)
+            .join(game_id.to_string())
+            .with_extension(extension)
+    }
+
+    fn tournament_path(&self, tournament_id: TournamentId) -> PathBuf {
+        self.directory
+            .join(TOURNAMENTS_DIRECTORY)
+            .join(tournament_id.to_string())
+            .with_extension(FILE_EXTENSION)
+    }
+
+    fn remove_file(path: PathBuf) -> io::Result<()> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reads every JSON file in a subdirectory, skipping those that cannot be read.
+    fn load_directory<T: DeserializeOwned>(&self, name: &str) -> io::Result<Vec<(PathBuf, T)>> {
+        let mut values = vec![];
+
+        for entry in fs::read_dir(self.directory.join(name))? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|x| x != FILE_EXTENSION) {
+                continue;
+            }
+
+            match serde_json::from_slice(&fs::read(&path)?) {
+                Ok(value) => values.push((path, value)),
+                Err(e) => log::warn!("skipping unreadable file {}: {}", path.display(), e),
+            }
+        }
+
+        Ok(values)
+    }
+
+    fn load_history(path: PathBuf) -> io::Result<Option<History>> {
+        let file = match fs::File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let mut entries = vec![];
+        for line in BufReader::new(file).lines() {
+            // Only the last line can be unreadable, if a crash cut it short
+            match serde_json::from_str(&line?) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => log::warn!(
+                    "skipping unreadable history entry in {}: {}",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+        Ok(Some(History::from_entries(entries)))
+    }
+
+    fn write_atomically(path: PathBuf, contents: Vec<u8>) -> io::Result<()> {
+        // Each write has its own temporary file so that concurrent writes never share one
+        let temp_path = path.with_extension(format!("{}.tmp", rand::random::<u32>()));
+
+        // Write to a temporary file first so that a crash never leaves a partial file behind
+        fs::write(&temp_path, contents)?;
+        fs::rename(temp_path, path)
+    }
+}
+
+impl Storage for FileStorage {
+    fn save_game(&self, game: &StoredGame) -> io::Result<()> {
+        FileStorage::write_atomically(
+            self.game_path(game.game_id, FILE_EXTENSION),
+            serde_json::to_vec(game)?,
+        )
+    }
+
+    fn append_history(&self, game_id: GameId, entries: &[HistoryEntry]) -> io::Result<()> {
+        let mut contents = vec![];
+        for entry in entries {
+            serde_json::to_writer(&mut contents, entry)?;
+            contents.push(b'\n');
+        }
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.game_path(game_id, HISTORY_EXTENSION))?
+            .write_all(&contents)
+    }
+
+    fn remove_game(&self, game_id: GameId) -> io::Result<()> {
+        FileStorage::remove_file(self.game_path(game_id, FILE_EXTENSION))?;
+        FileStorage::remove_file(self.game_path(game_id, HISTORY_EXTENSION))
+    }
+
+    fn load_games(&self) -> io::Result<Vec<StoredGame>> {
+        let mut games = vec![];
+
+        for (path, mut game) in self.load_directory::<StoredGame>(GAMES_DIRECTORY)? {
+            if let Some(history) =
+                FileStorage::load_history(path.with_extension(HISTORY_EXTENSION))?
+            {
+                game.history = history;
+            }
+            games.push(game);
+        }
+
+        Ok(games)
+    }
+
+    fn save_tournament(&self, tournament: &Tournament) -> io::Result<()> {
+        FileStorage::write_atomically(
+            self.tournament_path(tournament.tournament_id),
+            serde_json::to_vec(tournament)?,
+        )
+    }
+
+    fn remove_tournament(&self, tournament_id: TournamentId) -> io::Result<()> {
+        FileStorage::remove_file(self.tournament_path(tournament_id))
+    }
+
+    fn load_tournaments(&self) -> io::Result<Vec<Tournament>> {
+        Ok(self
+            .load_directory(TOURNAMENTS_DIRECTORY)?
+            .into_iter()
+            .map(|(_, tournament)| tournament)
+            .collect())
+    }
+
+    fn save_ratings(&self, ratings: &StoredRatings) -> io::Result<()> {
+        FileStorage::write_atomically(
+            self.directory.join(RATINGS_FILE),
+            serde_json::to_vec(ratings)?,
+        )
+    }
+
+    fn load_ratings(&self) -> io::Result<StoredRatings> {
+        match fs::read(self.directory.join(RATINGS_FILE)) {
+            Ok(contents) => Ok(serde_json::from_slice(&contents)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(StoredRatings::new()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::history::MoveRecord;
+    use crate::game::rating::RatingTable;
+    use tempfile::TempDir;
+
+    // The directory is removed when it is dropped, so it is kept alive alongside the storage
+    fn create_storage() -> (TempDir, FileStorage) {
+        let directory = tempfile::tempdir().unwrap();
+        let storage = FileStorage::new(directory.path().to_path_buf()).unwrap();
+        (directory, storage)
+    }
+
+    fn create_game() -> StoredGame {
+        let mut sessions = HashMap::new();
+        sessions.insert(SessionId::new(), Session::new(String::from("Player 1")));
+
+        StoredGame {
+            game_id: GameId::new(),
+            game_type: GameType::find("connect_4").unwrap(),
+            adapter: serde_json::json!({ "stage": "waiting" }),
+            sessions,
+            last_update: chrono::offset::Utc::now(),
+            history: History::default(),
+            rated: false,
+            clock: 1,
+            settings: GameSettings::default(),
+            deadline: None,
+            turn: vec![],
+            termination: None,
+            draw_offers: vec![],
+        }
+    }
+
+    #[test]
+    fn saved_games_are_loaded() {
+        let (_directory, storage) = create_storage();
+        let game = create_game();
+
+        storage.save_game(&game).unwrap();
+        storage.save_game(&game).unwrap();
+
+        let loaded = storage.load_games().unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].game_id, game.game_id);
+        assert_eq!(loaded[0].adapter, game.adapter);
+        assert_eq!(
+            loaded[0].sessions.keys().collect::<Vec<_>>(),
+            game.sessions.keys().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn appended_history_is_loaded() {
+        let (_directory, storage) = create_storage();
+        let game = create_game();
+        let mut history = History::default();
+
+        storage.save_game(&game).unwrap();
+        for clock in 1..=3 {
+            history.record_move(MoveRecord {
+                player: String::from("Player 1"),
+                payload: serde_json::json!({ "column": clock }),
+                timestamp: chrono::offset::Utc::now(),
+                clock,
+            });
+            storage
+                .append_history(game.game_id, history.get_unpersisted())
+                .unwrap();
+            history.mark_persisted();
+        }
+        storage.save_game(&game).unwrap();
+
+        let loaded = storage.load_games().unwrap();
+        let moves = loaded[0].history.get_moves();
+        assert_eq!(
+            moves.iter().map(|x| x.clock).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert!(loaded[0].history.get_unpersisted().is_empty());
+    }
+
+    #[test]
+    fn removed_games_are_not_loaded() {
+        let (_directory, storage) = create_storage();
+        let game = create_game();
+
+        storage.save_game(&game).unwrap();
+        storage.remove_game(game.game_id).unwrap();
+        storage.remove_game(game.game_id).unwrap();
+
+        assert!(storage.load_games().unwrap().is_empty());
+    }
+
+    #[test]
+    fn saved_ratings_are_loaded() {
+        let (_directory, storage) = create_storage();
+        assert!(storage.load_ratings().unwrap().is_empty());
+
+        let table = RatingTable::new(StoredRatings::new());
+        let players = vec![String::from("a"), String::from("b")];
+        table.record_game(
+            GameType::find("connect_4").unwrap(),
+            &players,
+            &players[..1],
+        );
+        table.save(&storage).unwrap();
+
+        let loaded = RatingTable::new(storage.load_ratings().unwrap());
+        let leaderboard = loaded.get_leaderboard(GameType::find("connect_4").unwrap());
+        assert_eq!(leaderboard.len(), 2);
+        assert_eq!(leaderboard[0].username, "a");
+        assert_eq!(leaderboard[0].rating.wins, 1);
+    }
+
+    #[test]
+    fn concurrent_rating_saves_keep_every_update() {
+        let (_directory, storage) = create_storage();
+        let table = RatingTable::new(StoredRatings::new());
+        let game_type = GameType::find("connect_4").unwrap();
+        let players = vec![String::from("a"), String::from("b")];
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    table.record_game(game_type, &players, &players[..1]);
+                    table.save(&storage).unwrap();
+                });
+            }
+        });
+
+        let loaded = RatingTable::new(storage.load_ratings().unwrap());
+        let leaderboard = loaded.get_leaderboard(game_type);
+        assert_eq!(leaderboard[0].rating.games_played, 8);
+    }
+}
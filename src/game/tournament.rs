@@ -0,0 +1,782 @@
+use crate::game::adapter::Stage;
+use crate::game::storage::Storage;
+use crate::game::{
+    validate_username, GameId, GameManager, GameSettings, GameType, SessionId, TournamentId,
+};
+use actix_web::http::StatusCode;
+use actix_web::{ResponseError, Result};
+use chrono::{DateTime, Duration, Utc};
+use derive_more::Display;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+const MIN_PLAYERS: usize = 2;
+const MAX_PLAYERS: usize = 64;
+const MAX_REMATCHES: usize = 2;
+// Long enough for bots to move, but short enough that one that has stopped cannot stall the bracket
+const MOVE_TIMEOUT_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Display)]
+pub enum TournamentError {
+    #[display(fmt = "no tournament with id {}", _0)]
+    TournamentNotFound(TournamentId),
+    #[display(fmt = "tournaments require a two-player game type")]
+    UnsupportedGameType,
+    #[display(
+        fmt = "tournaments require between {} and {} players",
+        MIN_PLAYERS,
+        MAX_PLAYERS
+    )]
+    InvalidPlayerCount,
+    #[display(fmt = "player {} is entered more than once", _0)]
+    DuplicatePlayer(String),
+}
+
+impl ResponseError for TournamentError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            TournamentError::TournamentNotFound(_) => StatusCode::NOT_FOUND,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TournamentFormat {
+    RoundRobin,
+    Knockout,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchStatus {
+    InProgress,
+    Finished,
+    Abandoned,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Match {
+    pub round: usize,
+    // None if the game could not be created
+    pub game_id: Option<GameId>,
+    pub players: Vec<String>,
+    pub status: MatchStatus,
+    pub winners: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct Standing {
+    pub username: String,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub points: f64,
+    pub eliminated: bool,
+}
+
+#[derive(Serialize)]
+pub struct TournamentSummary {
+    pub tournament_id: TournamentId,
+    pub game_type: GameType,
+    pub format: TournamentFormat,
+    pub players: Vec<String>,
+    pub stage: Stage,
+    pub round: usize,
+    pub winners: Vec<String>,
+    pub last_updated: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct TournamentDetails {
+    #[serde(flatten)]
+    pub summary: TournamentSummary,
+    pub matches: Vec<Match>,
+    pub standings: Vec<Standing>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Tournament {
+    pub tournament_id: TournamentId,
+    game_type: GameType,
+    format: TournamentFormat,
+    players: Vec<String>,
+    sessions: HashMap<String, SessionId>,
+    stage: Stage,
+    round: usize,
+    matches: Vec<Match>,
+    // Players that have not been knocked out, in seed order
+    remaining: Vec<String>,
+    winners: Vec<String>,
+    last_update: DateTime<Utc>,
+}
+
+/// Pairs every player with every other player over a number of rounds using the circle method,
+/// alternating who takes the first seat.
+fn round_robin_pairings(players: usize, round: usize) -> Vec<(usize, usize)> {
+    let slots = players + players % 2;
+    let rotate = |i: usize| {
+        if i == 0 {
+            0
+        } else {
+            (i + round - 1) % (slots - 1) + 1
+        }
+    };
+
+    (0..slots / 2)
+        .map(|i| (rotate(i), rotate(slots - 1 - i)))
+        .filter(|&(a, b)| a < players && b < players)
+        .map(|(a, b)| {
+            if round.is_multiple_of(2) {
+                (a, b)
+            } else {
+                (b, a)
+            }
+        })
+        .collect()
+}
+
+impl Tournament {
+    fn total_rounds(&self) -> Option<usize> {
+        match self.format {
+            TournamentFormat::RoundRobin => Some(self.players.len() + self.players.len() % 2 - 1),
+            TournamentFormat::Knockout => None,
+        }
+    }
+
+    fn create_match(&mut self, game_manager: &GameManager, players: Vec<String>) {
+        let options = self.game_type.info().options_for_players(players.len());
+        let settings = GameSettings {
+            move_timeout_ms: Some(MOVE_TIMEOUT_MS),
+            join_token: None,
+        };
+        let created = game_manager.create_game(self.game_type, options, settings);
+        let (game_id, mut status) = match created {
+            Ok(game_id) => (Some(game_id), MatchStatus::InProgress),
+            Err(e) => {
+                log::error!("failed to create game for {}: {}", self.tournament_id, e);
+                (None, MatchStatus::Abandoned)
+            }
+        };
+
+        if let Some(game_id) = game_id {
+            for player in players.iter() {
+                let session_id = self.sessions[player];
+                if let Err(e) = game_manager.assign_seat(game_id, player.clone(), session_id) {
+                    log::error!("failed to seat {} in {}: {}", player, game_id, e);
+                    status = MatchStatus::Abandoned;
+                }
+            }
+        }
+
+        self.matches.push(Match {
+            round: self.round,
+            game_id,
+            players,
+            status,
+            winners: vec![],
+        });
+        if status == MatchStatus::Abandoned && self.format == TournamentFormat::Knockout {
+            self.resolve_knockout_match(game_manager, self.matches.len() - 1);
+        }
+    }
+
+    fn start_round(&mut self, game_manager: &GameManager) {
+        self.round += 1;
+
+        let pairings = match self.format {
+            TournamentFormat::RoundRobin => {
+                round_robin_pairings(self.players.len(), self.round - 1)
+                    .into_iter()
+                    .map(|(a, b)| vec![self.players[a].clone(), self.players[b].clone()])
+                    .collect_vec()
+            }
+            TournamentFormat::Knockout => {
+                // The highest seeds get byes until the number of remaining players is a power of two
+                let remaining = &self.remaining;
+                let byes = remaining.len().next_power_of_two() - remaining.len();
+                let contested = &remaining[byes..];
+                (0..contested.len() / 2)
+                    .map(|i| {
+                        vec![
+                            contested[i].clone(),
+                            contested[contested.len() - 1 - i].clone(),
+                        ]
+                    })
+                    .collect_vec()
+            }
+        };
+
+        for players in pairings {
+            self.create_match(game_manager, players);
+        }
+    }
+
+    fn seed(&self, player: &str) -> usize {
+        self.players.iter().position(|x| x == player).unwrap()
+    }
+
+    fn resolve_knockout_match(&mut self, game_manager: &GameManager, index: usize) {
+        let Match {
+            round,
+            ref players,
+            status,
+            ref winners,
+            ..
+        } = self.matches[index];
+        let players = players.clone();
+
+        let loser = if status == MatchStatus::Finished && winners.len() == 1 {
+            players.iter().find(|&x| x != &winners[0]).cloned()
+        } else {
+            let played = self
+                .matches
+                .iter()
+                .filter(|m| m.round == round && m.players.iter().all(|x| players.contains(x)))
+                .count();
+
+            if status == MatchStatus::Finished && played <= MAX_REMATCHES {
+                self.create_match(game_manager, players.iter().rev().cloned().collect());
+                None
+            } else {
+                // Unresolved matches are decided in favour of the higher seed
+                players.iter().max_by_key(|x| self.seed(x)).cloned()
+            }
+        };
+
+        if let Some(loser) = loser {
+            self.remaining.retain(|x| x != &loser);
+        }
+    }
+
+    fn finish(&mut self) {
+        self.stage = Stage::Ended;
+        self.winners = match self.format {
+            TournamentFormat::RoundRobin => {
+                let standings = self.get_standings();
+                let best = standings.first().map_or(0.0, |x| x.points);
+                standings
+                    .into_iter()
+                    .filter(|x| x.points == best)
+                    .map(|x| x.username)
+                    .collect()
+            }
+            TournamentFormat::Knockout => self.remaining.clone(),
+        };
+    }
+
+    /// Records finished matches and starts the next round once the current one is over, returning
+    /// whether anything changed.
+    fn advance(&mut self, game_manager: &GameManager) -> bool {
+        if self.stage == Stage::Ended {
+            return false;
+        }
+
+        let mut changed = false;
+        for index in 0..self.matches.len() {
+            let game_match = &mut self.matches[index];
+            if game_match.status != MatchStatus::InProgress {
+                continue;
+            }
+
+            match game_match.game_id.map(|x| game_manager.get_state(x, None)) {
+                Some(Ok(state)) if state.stage == Stage::Ended => {
+                    game_match.status = MatchStatus::Finished;
+                    game_match.winners = state.winners;
+                }
+                Some(Ok(_)) => continue,
+                _ => game_match.status = MatchStatus::Abandoned,
+            }
+
+            changed = true;
+            if self.format == TournamentFormat::Knockout {
+                self.resolve_knockout_match(game_manager, index);
+            }
+        }
+
+        if !changed {
+            return false;
+        }
+        self.last_update = chrono::offset::Utc::now();
+        self.start_next_round(game_manager);
+        true
+    }
+
+    /// Starts rounds until one has a match in progress, since a round whose games could not be
+    /// created is over as soon as it starts, or finishes the tournament once there are none left.
+    fn start_next_round(&mut self, game_manager: &GameManager) {
+        while self.stage != Stage::Ended
+            && !self
+                .matches
+                .iter()
+                .any(|x| x.status == MatchStatus::InProgress)
+        {
+            let more_rounds = match self.total_rounds() {
+                Some(total_rounds) => self.round < total_rounds,
+                None => self.remaining.len() > 1,
+            };
+
+            if more_rounds {
+                self.start_round(game_manager);
+            } else {
+                self.finish();
+            }
+        }
+    }
+
+    fn get_standings(&self) -> Vec<Standing> {
+        let mut standings = self
+            .players
+            .iter()
+            .map(|username| Standing {
+                username: username.clone(),
+                wins: 0,
+                draws: 0,
+                losses: 0,
+                points: 0.0,
+                eliminated: self.format == TournamentFormat::Knockout
+                    && !self.remaining.contains(username),
+            })
+            .collect_vec();
+
+        for game_match in self
+            .matches
+            .iter()
+            .filter(|x| x.status == MatchStatus::Finished)
+        {
+            for standing in standings
+                .iter_mut()
+                .filter(|x| game_match.players.contains(&x.username))
+            {
+                if game_match.winners.is_empty() {
+                    standing.draws += 1;
+                    standing.points += 0.5;
+                } else if game_match.winners.contains(&standing.username) {
+                    standing.wins += 1;
+                    standing.points += 1.0;
+                } else {
+                    standing.losses += 1;
+                }
+            }
+        }
+
+        standings
+            .into_iter()
+            .sorted_by(|a, b| {
+                a.eliminated
+                    .cmp(&b.eliminated)
+                    .then(b.points.total_cmp(&a.points))
+                    .then(b.wins.cmp(&a.wins))
+            })
+            .collect()
+    }
+
+    fn get_summary(&self) -> TournamentSummary {
+        TournamentSummary {
+            tournament_id: self.tournament_id,
+            game_type: self.game_type,
+            format: self.format,
+            players: self.players.clone(),
+            stage: self.stage,
+            round: self.round,
+            winners: self.winners.clone(),
+            last_updated: self.last_update,
+        }
+    }
+}
+
+pub struct TournamentManager {
+    tournaments: Mutex<HashMap<TournamentId, Tournament>>,
+    storage: Arc<dyn Storage>,
+}
+
+impl TournamentManager {
+    pub fn new(storage: Arc<dyn Storage>) -> std::io::Result<Self> {
+        let tournaments = storage
+            .load_tournaments()?
+            .into_iter()
+            .map(|x| (x.tournament_id, x))
+            .collect();
+
+        Ok(TournamentManager {
+            tournaments: Mutex::new(tournaments),
+            storage,
+        })
+    }
+
+    fn persist(&self, tournament: &Tournament) {
+        if let Err(e) = self.storage.save_tournament(tournament) {
+            log::error!(
+                "failed to persist tournament {}: {}",
+                tournament.tournament_id,
+                e
+            );
+        }
+    }
+
+    pub fn create_tournament(
+        &self,
+        game_manager: &GameManager,
+        game_type: GameType,
+        format: TournamentFormat,
+        players: Vec<String>,
+    ) -> Result<(TournamentId, HashMap<String, SessionId>)> {
+        if !game_type.info().supports_players(2) {
+            return Err(actix_web::Error::from(TournamentError::UnsupportedGameType));
+        }
+
+        if players.len() < MIN_PLAYERS || players.len() > MAX_PLAYERS {
+            return Err(actix_web::Error::from(TournamentError::InvalidPlayerCount));
+        }
+
+        let mut seen = HashSet::new();
+        for player in players.iter() {
+            validate_username(player)?;
+            if !seen.insert(player) {
+                return Err(actix_web::Error::from(TournamentError::DuplicatePlayer(
+                    player.clone(),
+                )));
+            }
+        }
+
+        let sessions: HashMap<String, SessionId> = players
+            .iter()
+            .map(|x| (x.clone(), SessionId::new()))
+            .collect();
+
+        let mut tournaments = self.tournaments.lock().unwrap();
+        tournaments.retain(|&tournament_id, x| {
+            let retain =
+                x.stage != Stage::Ended || x.last_update + Duration::hours(1) >= Utc::now();
+            if !retain {
+                if let Err(e) = self.storage.remove_tournament(tournament_id) {
+                    log::error!(
+                        "failed to remove tournament {} from storage: {}",
+                        tournament_id,
+                        e
+                    );
+                }
+            }
+            retain
+        });
+
+        let tournament_id = loop {
+            let tournament_id = TournamentId::new();
+            if !tournaments.contains_key(&tournament_id) {
+                break tournament_id;
+            }
+        };
+
+        let mut tournament = Tournament {
+            tournament_id,
+            game_type,
+            format,
+            remaining: players.clone(),
+            players,
+            sessions: sessions.clone(),
+            stage: Stage::InProgress,
+            round: 0,
+            matches: vec![],
+            winners: vec![],
+            last_update: chrono::offset::Utc::now(),
+        };
+        tournament.start_next_round(game_manager);
+        self.persist(&tournament);
+        tournaments.insert(tournament_id, tournament);
+
+        Ok((tournament_id, sessions))
+    }
+
+    pub fn list_tournaments(&self) -> Vec<TournamentSummary> {
+        self.tournaments
+            .lock()
+            .unwrap()
+            .values()
+            .map(Tournament::get_summary)
+            .sorted_by(|a, b| b.last_updated.cmp(&a.last_updated))
+            .collect()
+    }
+
+    pub fn get_tournament(&self, tournament_id: TournamentId) -> Result<TournamentDetails> {
+        let tournaments = self.tournaments.lock().unwrap();
+        let tournament = tournaments.get(&tournament_id).ok_or_else(|| {
+            actix_web::Error::from(TournamentError::TournamentNotFound(tournament_id))
+        })?;
+
+        Ok(TournamentDetails {
+            summary: tournament.get_summary(),
+            matches: tournament.matches.clone(),
+            standings: tournament.get_standings(),
+        })
+    }
+
+    /// Records the results of finished games and starts the next round of every tournament
+    /// whose current round is over.
+    pub fn advance(&self, game_manager: &GameManager) {
+        for tournament in self.tournaments.lock().unwrap().values_mut() {
+            if tournament.advance(game_manager) {
+                self.persist(tournament);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::storage::{FileStorage, MemoryStorage};
+    use serde_json::json;
+
+    fn create_knockout(
+        storage: Arc<dyn Storage>,
+        players: &[&str],
+    ) -> (
+        GameManager,
+        TournamentManager,
+        TournamentId,
+        HashMap<String, SessionId>,
+    ) {
+        let game_manager = GameManager::new(storage.clone()).unwrap();
+        let tournament_manager = TournamentManager::new(storage).unwrap();
+        let (tournament_id, sessions) = tournament_manager
+            .create_tournament(
+                &game_manager,
+                GameType::find("connect_4").unwrap(),
+                TournamentFormat::Knockout,
+                players.iter().map(|x| x.to_string()).collect(),
+            )
+            .unwrap();
+        (game_manager, tournament_manager, tournament_id, sessions)
+    }
+
+    fn current_matches(manager: &TournamentManager, tournament_id: TournamentId) -> Vec<Match> {
+        manager
+            .get_tournament(tournament_id)
+            .unwrap()
+            .matches
+            .into_iter()
+            .filter(|x| x.status == MatchStatus::InProgress)
+            .collect()
+    }
+
+    // Every column is filled from the bottom, except that column 4 is started before column 3 so
+    // that no row has four pieces of one player
+    const DRAWN_GAME: [usize; 42] = [
+        0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 4, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4,
+        5, 5, 5, 5, 5, 5, 6, 6, 6, 6, 6, 6,
+    ];
+
+    /// Drops pieces into the columns in order, each one for whichever player is to move.
+    fn play(
+        game_manager: &GameManager,
+        sessions: &HashMap<String, SessionId>,
+        game_match: &Match,
+        columns: &[usize],
+    ) {
+        for &column in columns {
+            let state = game_manager
+                .get_state(game_match.game_id.unwrap(), None)
+                .unwrap();
+            game_manager
+                .receive_move(
+                    game_match.game_id.unwrap(),
+                    sessions[&state.can_move[0]],
+                    json!({ "game_type": "connect_4", "column": column }),
+                )
+                .unwrap();
+        }
+    }
+
+    /// Plays a game that the player wins by filling the first column.
+    fn win(
+        game_manager: &GameManager,
+        sessions: &HashMap<String, SessionId>,
+        game_match: &Match,
+        player: &str,
+    ) {
+        let columns: &[usize] = if game_match.players[0] == player {
+            &[0, 1, 0, 2, 0, 1, 0]
+        } else {
+            &[1, 0, 2, 0, 1, 0, 2, 0]
+        };
+        play(game_manager, sessions, game_match, columns);
+    }
+
+    #[test]
+    fn knockout_advances_until_one_player_remains() {
+        let players = ["a", "b", "c", "d", "e"];
+        let (game_manager, manager, tournament_id, sessions) =
+            create_knockout(Arc::new(MemoryStorage), &players);
+
+        // The three highest seeds get byes, and then the higher seed wins every match
+        let mut rounds = vec![];
+        loop {
+            let matches = current_matches(&manager, tournament_id);
+            if matches.is_empty() {
+                break;
+            }
+            rounds.push(matches.iter().map(|x| x.players.join("-")).collect_vec());
+            for game_match in matches.iter() {
+                let higher_seed = game_match.players.iter().min().unwrap();
+                win(&game_manager, &sessions, game_match, higher_seed);
+            }
+            manager.advance(&game_manager);
+        }
+
+        assert_eq!(rounds, vec![vec!["d-e"], vec!["a-d", "b-c"], vec!["a-b"]]);
+        let details = manager.get_tournament(tournament_id).unwrap();
+        assert_eq!(details.summary.stage, Stage::Ended);
+        assert_eq!(details.summary.round, 3);
+        assert_eq!(details.summary.winners, vec!["a"]);
+        assert_eq!(details.standings[0].username, "a");
+        assert!(details.standings[1..].iter().all(|x| x.eliminated));
+    }
+
+    #[test]
+    fn games_for_a_range_of_players_are_created_for_two() {
+        let game_manager = GameManager::new(Arc::new(MemoryStorage)).unwrap();
+        let manager = TournamentManager::new(Arc::new(MemoryStorage)).unwrap();
+        let (tournament_id, _) = manager
+            .create_tournament(
+                &game_manager,
+                GameType::find("snake").unwrap(),
+                TournamentFormat::RoundRobin,
+                vec![String::from("a"), String::from("b")],
+            )
+            .unwrap();
+
+        let matches = current_matches(&manager, tournament_id);
+        assert_eq!(matches.len(), 1);
+        let state = game_manager
+            .get_state(matches[0].game_id.unwrap(), None)
+            .unwrap();
+        assert_eq!(state.stage, Stage::InProgress);
+        assert!(manager
+            .create_tournament(
+                &game_manager,
+                GameType::find("hearts").unwrap(),
+                TournamentFormat::RoundRobin,
+                vec![String::from("a"), String::from("b")],
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn matches_whose_games_cannot_be_created_are_abandoned() {
+        let game_manager = GameManager::new(Arc::new(MemoryStorage)).unwrap();
+        let manager = TournamentManager::new(Arc::new(MemoryStorage)).unwrap();
+        let (tournament_id, _) = manager
+            .create_tournament(
+                &game_manager,
+                GameType::find("snake").unwrap(),
+                TournamentFormat::Knockout,
+                vec![String::from("a"), String::from("b")],
+            )
+            .unwrap();
+
+        {
+            let mut tournaments = manager.tournaments.lock().unwrap();
+            let tournament = tournaments.get_mut(&tournament_id).unwrap();
+            tournament.matches.clear();
+            // Snake games cannot be created for a single player
+            tournament.create_match(&game_manager, vec![String::from("b")]);
+            tournament.start_next_round(&game_manager);
+        }
+
+        let details = manager.get_tournament(tournament_id).unwrap();
+        assert_eq!(details.matches.len(), 1);
+        assert_eq!(details.matches[0].status, MatchStatus::Abandoned);
+        assert!(details.matches[0].game_id.is_none());
+        assert_eq!(details.summary.stage, Stage::Ended);
+        assert_eq!(details.summary.winners, vec!["a"]);
+    }
+
+    #[test]
+    fn tournament_games_have_a_move_timeout() {
+        let (game_manager, manager, tournament_id, _) =
+            create_knockout(Arc::new(MemoryStorage), &["a", "b"]);
+
+        let matches = current_matches(&manager, tournament_id);
+        let state = game_manager
+            .get_state(matches[0].game_id.unwrap(), None)
+            .unwrap();
+        assert!(state.deadline.is_some());
+    }
+
+    #[test]
+    fn drawn_knockout_matches_are_replayed() {
+        let (game_manager, manager, tournament_id, sessions) =
+            create_knockout(Arc::new(MemoryStorage), &["a", "b"]);
+
+        for seats in [["a", "b"], ["b", "a"], ["a", "b"]] {
+            let matches = current_matches(&manager, tournament_id);
+            assert_eq!(matches.len(), 1);
+            assert_eq!(matches[0].players, seats);
+            play(&game_manager, &sessions, &matches[0], &DRAWN_GAME);
+            manager.advance(&game_manager);
+        }
+
+        // After the last rematch is drawn as well, the higher seed goes through
+        let details = manager.get_tournament(tournament_id).unwrap();
+        assert_eq!(details.matches.len(), MAX_REMATCHES + 1);
+        assert_eq!(details.summary.winners, vec!["a"]);
+    }
+
+    #[test]
+    fn tournaments_survive_a_restart() {
+        let directory = tempfile::tempdir().unwrap();
+        let storage = || -> Arc<dyn Storage> {
+            Arc::new(FileStorage::new(directory.path().to_path_buf()).unwrap())
+        };
+        let (game_manager, manager, tournament_id, sessions) =
+            create_knockout(storage(), &["a", "b"]);
+        drop((game_manager, manager));
+
+        let game_manager = GameManager::new(storage()).unwrap();
+        let manager = TournamentManager::new(storage()).unwrap();
+        let matches = current_matches(&manager, tournament_id);
+        assert_eq!(matches.len(), 1);
+        win(&game_manager, &sessions, &matches[0], "a");
+        manager.advance(&game_manager);
+        drop(manager);
+
+        let manager = TournamentManager::new(storage()).unwrap();
+        let details = manager.get_tournament(tournament_id).unwrap();
+        assert_eq!(details.summary.stage, Stage::Ended);
+        assert_eq!(details.summary.winners, vec!["a"]);
+    }
+
+    fn all_pairings(players: usize) -> Vec<Vec<(usize, usize)>> {
+        let rounds = players + players % 2 - 1;
+        (0..rounds)
+            .map(|round| round_robin_pairings(players, round))
+            .collect()
+    }
+
+    #[test]
+    fn round_robin_pairs_everyone_once() {
+        for players in 2..10 {
+            let pairs = all_pairings(players)
+                .into_iter()
+                .flatten()
+                .map(|(a, b)| (a.min(b), a.max(b)))
+                .collect_vec();
+
+            assert_eq!(pairs.len(), players * (players - 1) / 2);
+            assert!(pairs.iter().all_unique());
+        }
+    }
+
+    #[test]
+    fn round_robin_plays_once_per_round() {
+        for round in all_pairings(7) {
+            let seated = round.iter().flat_map(|&(a, b)| [a, b]).collect_vec();
+
+            assert_eq!(seated.len(), 6);
+            assert!(seated.iter().all_unique());
+        }
+    }
+}
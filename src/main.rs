@@ -1,14 +1,23 @@
 mod api;
+mod events;
 mod game;
 mod notify;
+mod socket;
 
 use actix_cors::Cors;
 use actix_web::error::InternalError;
 use actix_web::middleware::Logger;
 use actix_web::{web, App, HttpResponse, HttpServer};
+use game::matchmaking::MatchmakingQueue;
+use game::storage::{FileStorage, MemoryStorage, Storage};
+use game::tournament::TournamentManager;
 use std::env;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 const MAX_JSON_PAYLOAD_SIZE: usize = 4096;
+const MAINTENANCE_INTERVAL: Duration = Duration::from_millis(100);
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -17,7 +26,37 @@ async fn main() -> std::io::Result<()> {
     let host = env::var("HOST").unwrap_or("127.0.0.1".to_string());
     let port = env::var("PORT").unwrap_or("8080".to_string());
 
-    let game_manager = web::Data::new(game::GameManager::new());
+    let storage: Arc<dyn Storage> = match env::var("STORAGE_DIR") {
+        Ok(directory) => Arc::new(FileStorage::new(PathBuf::from(directory))?),
+        Err(_) => Arc::new(MemoryStorage),
+    };
+    let game_manager = web::Data::new(game::GameManager::new(storage.clone())?);
+    let tournament_manager = web::Data::new(TournamentManager::new(storage)?);
+    let matchmaking_queue = web::Data::new(MatchmakingQueue::new());
+
+    {
+        let game_manager = game_manager.clone();
+        let tournament_manager = tournament_manager.clone();
+        actix_web::rt::spawn(async move {
+            let mut interval = tokio::time::interval(MAINTENANCE_INTERVAL);
+            loop {
+                interval.tick().await;
+                game_manager.expire_deadlines();
+                tournament_manager.advance(&game_manager);
+            }
+        });
+    }
+
+    {
+        let game_manager = game_manager.clone();
+        actix_web::rt::spawn(async move {
+            loop {
+                game_manager.wait_for_tick().await;
+                game_manager.run_due_ticks();
+            }
+        });
+    }
+
     let json_config = web::JsonConfig::default()
         .limit(MAX_JSON_PAYLOAD_SIZE)
         .error_handler(|err, _req| {
@@ -53,16 +92,28 @@ async fn main() -> std::io::Result<()> {
             .wrap(Logger::default())
             .wrap(Cors::permissive())
             .app_data(game_manager.clone())
+            .app_data(tournament_manager.clone())
+            .app_data(matchmaking_queue.clone())
             .app_data(json_config.clone())
             .app_data(query_config.clone())
             .app_data(path_config.clone())
             .service(actix_files::Files::new("/static", "./static").show_files_listing())
             .service(api::create_game)
+            .service(api::game_types)
             .service(api::list_games)
+            .service(api::leaderboard)
+            .service(api::enqueue)
+            .service(api::create_tournament)
+            .service(api::list_tournaments)
+            .service(api::get_tournament)
             .service(api::join_game)
             .service(api::get_state)
             .service(api::submit_move)
             .service(api::wait_for_update)
+            .service(api::get_history)
+            .service(api::get_state_at)
+            .service(api::game_socket)
+            .service(api::game_events)
     })
     .bind(format!("{}:{}", host, port))?
     .run()
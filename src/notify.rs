@@ -3,8 +3,9 @@ use actix_web::{Error, ResponseError};
 use derive_more::Display;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
+use tokio::stream::Stream;
 use tokio::sync::broadcast;
-use tokio::sync::broadcast::TryRecvError;
+use tokio::sync::broadcast::RecvError;
 use tokio::time::timeout;
 
 #[derive(Debug, Display, Clone)]
@@ -39,6 +40,15 @@ impl Notifier {
         let _ = self.sender.send(self.clock.fetch_add(1, Ordering::SeqCst));
     }
 
+    pub fn clock(&self) -> usize {
+        self.clock.load(Ordering::SeqCst)
+    }
+
+    /// Continues from a clock saved before a restart, so that clocks never go back.
+    pub fn restore_clock(&self, clock: usize) {
+        self.clock.fetch_max(clock, Ordering::SeqCst);
+    }
+
     pub fn subscribe(&self) -> Subscription {
         Subscription {
             receiver: self.sender.subscribe(),
@@ -62,11 +72,7 @@ impl Subscription {
         }
     }
 
-    pub fn did_update(&mut self) -> actix_web::Result<bool> {
-        match self.receiver.try_recv() {
-            Ok(_) => Ok(true),
-            Err(TryRecvError::Empty) => Ok(false),
-            Err(_) => Err(Error::from(NotifierError {})),
-        }
+    pub fn into_stream(self) -> impl Stream<Item = Result<usize, RecvError>> {
+        self.receiver
     }
 }
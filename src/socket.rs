@@ -0,0 +1,144 @@
+// WebSocket streaming of game updates
+
+use crate::game::{GameId, GameManager, GameUpdate, SessionId};
+use actix::{Actor, ActorContext, AsyncContext, StreamHandler};
+use actix_web::web;
+use actix_web_actors::ws;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+use tokio::sync::broadcast::RecvError;
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientFrame {
+    SubmitMove {
+        session_id: SessionId,
+        payload: Value,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerFrame {
+    Update(GameUpdate),
+    SubmitMoveResult { success: bool },
+    Error { message: String },
+}
+
+pub struct GameSocket {
+    game_id: GameId,
+    game_manager: web::Data<GameManager>,
+}
+
+impl GameSocket {
+    pub fn new(game_id: GameId, game_manager: web::Data<GameManager>) -> Self {
+        GameSocket {
+            game_id,
+            game_manager,
+        }
+    }
+
+    fn send_frame(ctx: &mut ws::WebsocketContext<Self>, frame: &ServerFrame) {
+        ctx.text(serde_json::to_string(frame).unwrap());
+    }
+
+    fn send_update(&self, ctx: &mut ws::WebsocketContext<Self>) {
+        match self.game_manager.get_update(self.game_id) {
+            Ok(update) => GameSocket::send_frame(ctx, &ServerFrame::Update(update)),
+            Err(e) => {
+                GameSocket::send_frame(
+                    ctx,
+                    &ServerFrame::Error {
+                        message: e.to_string(),
+                    },
+                );
+                ctx.close(None);
+                ctx.stop();
+            }
+        }
+    }
+
+    fn handle_text(&self, ctx: &mut ws::WebsocketContext<Self>, text: &str) {
+        let frame = match serde_json::from_str::<ClientFrame>(text) {
+            Ok(frame) => frame,
+            Err(e) => {
+                return GameSocket::send_frame(
+                    ctx,
+                    &ServerFrame::Error {
+                        message: e.to_string(),
+                    },
+                )
+            }
+        };
+
+        let response = match frame {
+            ClientFrame::SubmitMove {
+                session_id,
+                payload,
+            } => match self
+                .game_manager
+                .receive_move(self.game_id, session_id, payload)
+            {
+                Ok(()) => ServerFrame::SubmitMoveResult { success: true },
+                Err(e) => ServerFrame::Error {
+                    message: e.to_string(),
+                },
+            },
+        };
+
+        GameSocket::send_frame(ctx, &response);
+    }
+}
+
+impl Actor for GameSocket {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        // Subscribe before sending the initial state so that no update is missed
+        match self.game_manager.subscribe(self.game_id) {
+            Ok(subscription) => {
+                ctx.add_stream(subscription.into_stream());
+                self.send_update(ctx);
+            }
+            Err(_) => ctx.stop(),
+        }
+
+        ctx.run_interval(HEARTBEAT_INTERVAL, |_, ctx| ctx.ping(b""));
+    }
+}
+
+impl StreamHandler<Result<usize, RecvError>> for GameSocket {
+    fn handle(&mut self, _: Result<usize, RecvError>, ctx: &mut Self::Context) {
+        // Every update carries the full state, so lagging behind is harmless
+        self.send_update(ctx);
+    }
+
+    fn finished(&mut self, ctx: &mut Self::Context) {
+        ctx.close(None);
+        ctx.stop();
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for GameSocket {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match msg {
+            Ok(ws::Message::Ping(msg)) => ctx.pong(&msg),
+            Ok(ws::Message::Text(text)) => self.handle_text(ctx, &text),
+            Ok(ws::Message::Binary(_)) => GameSocket::send_frame(
+                ctx,
+                &ServerFrame::Error {
+                    message: String::from("binary frames are not supported"),
+                },
+            ),
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            Err(_) => ctx.stop(),
+            _ => (),
+        }
+    }
+}